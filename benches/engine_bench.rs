@@ -0,0 +1,48 @@
+//! Benchmarks for engine construction and CSV processing.
+//!
+//! Run with `cargo bench`. Compares a default engine against one
+//! pre-sized with `PaymentsEngine::with_capacity` on inputs with many clients.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use payments_engine::PaymentsEngine;
+use std::fmt::Write;
+
+/// Builds a CSV with one deposit for each of `clients` clients.
+fn deposits_csv(clients: u16) -> String {
+    let mut csv = String::from("type,client,tx,amount\n");
+    for client in 0..clients {
+        writeln!(csv, "deposit,{},{},1.0", client, client).unwrap();
+    }
+    csv
+}
+
+fn bench_capacity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process_csv");
+    for clients in [10_000u16, 50_000] {
+        let csv = deposits_csv(clients);
+        let size = clients as usize;
+
+        group.bench_with_input(BenchmarkId::new("new", clients), &csv, |b, csv| {
+            b.iter(|| {
+                let mut engine = PaymentsEngine::new();
+                engine.process_csv_bytes(black_box(csv.as_bytes())).unwrap();
+                engine
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("with_capacity", clients),
+            &csv,
+            |b, csv| {
+                b.iter(|| {
+                    let mut engine = PaymentsEngine::with_capacity(size, size);
+                    engine.process_csv_bytes(black_box(csv.as_bytes())).unwrap();
+                    engine
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_capacity);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+//! Throughput benchmarks for CSV processing and output.
+//!
+//! Run with `cargo bench --bench throughput`. Processing groups report
+//! rows per second; output groups report accounts per second.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use payments_engine::PaymentsEngine;
+use std::fmt::Write;
+
+const ROWS: u32 = 10_000;
+
+/// Builds a CSV with `ROWS` deposits spread over 1,000 clients.
+fn deposits_csv() -> String {
+    let mut csv = String::from("type,client,tx,amount\n");
+    for tx in 0..ROWS {
+        writeln!(csv, "deposit,{},{},1.5", tx % 1000, tx).unwrap();
+    }
+    csv
+}
+
+/// Builds a CSV with `ROWS` rows cycling through deposit, withdrawal,
+/// dispute, resolve and a second deposit.
+fn mixed_csv() -> String {
+    let mut csv = String::from("type,client,tx,amount\n");
+    for tx in (0..ROWS).step_by(5) {
+        let client = tx % 1000;
+        writeln!(csv, "deposit,{},{},10.0", client, tx).unwrap();
+        writeln!(csv, "withdrawal,{},{},2.5", client, tx + 1).unwrap();
+        writeln!(csv, "dispute,{},{},", client, tx).unwrap();
+        writeln!(csv, "resolve,{},{},", client, tx).unwrap();
+        writeln!(csv, "deposit,{},{},0.1234", client, tx + 4).unwrap();
+    }
+    csv
+}
+
+/// Builds a CSV with `ROWS` rows where every deposit is disputed and resolved.
+fn dispute_heavy_csv() -> String {
+    let mut csv = String::from("type,client,tx,amount\n");
+    for tx in (0..ROWS).step_by(3) {
+        let client = tx % 1000;
+        writeln!(csv, "deposit,{},{},5.0", client, tx).unwrap();
+        writeln!(csv, "dispute,{},{},", client, tx).unwrap();
+        writeln!(csv, "resolve,{},{},", client, tx).unwrap();
+    }
+    csv
+}
+
+fn process(csv: &str) -> PaymentsEngine {
+    let mut engine = PaymentsEngine::new();
+    engine.process_csv_bytes(csv.as_bytes()).unwrap();
+    engine
+}
+
+fn bench_process(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process_csv");
+    for (name, csv) in [
+        ("10k_deposits", deposits_csv()),
+        ("10k_mixed", mixed_csv()),
+        ("dispute_heavy", dispute_heavy_csv()),
+    ] {
+        let rows = csv.lines().count() as u64 - 1;
+        group.throughput(Throughput::Elements(rows));
+        group.bench_function(name, |b| b.iter(|| black_box(process(black_box(&csv)))));
+    }
+    group.finish();
+}
+
+fn bench_write_output(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write_output");
+    for (name, csv) in [("10k_deposits", deposits_csv()), ("10k_mixed", mixed_csv())] {
+        let engine = process(&csv);
+        group.throughput(Throughput::Elements(engine.accounts_iter().count() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(engine.write_output_to_vec().unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_process, bench_write_output);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+//! Feeds arbitrary bytes to the CSV pipeline.
+//!
+//! Malformed input must be logged and skipped, never panic. Run with
+//! `cargo +nightly fuzz run fuzz_process_csv` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use payments_engine::PaymentsEngine;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let mut engine = PaymentsEngine::new();
+    engine
+        .process_csv(Cursor::new(data))
+        .expect("lenient processing never fails on in-memory input");
+    engine
+        .write_output(std::io::sink())
+        .expect("writing accounts to a sink never fails");
+});
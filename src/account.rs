@@ -3,7 +3,8 @@
 //! Maintains the invariant: `total == available + held` at all times.
 
 use crate::decimal::Decimal4;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Represents a client's account state.
 ///
@@ -19,7 +20,14 @@ use serde::Serialize;
 /// original deposit is disputed. The dispute moves the full deposit amount to
 /// `held`, which can result in `available` going negative. The invariant
 /// `total == available + held` is still maintained.
-#[derive(Debug, Clone, Serialize)]
+///
+/// # Serialization
+///
+/// Serializes to and deserializes from the `client,available,held,total,locked`
+/// format written by [`PaymentsEngine::write_output`](crate::PaymentsEngine::write_output).
+/// Lifetime totals, held transaction IDs and the audit log are not part of
+/// that format and start empty when deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClientAccount {
     /// Unique client identifier (u16).
     pub client: u16,
@@ -35,6 +43,108 @@ pub struct ClientAccount {
 
     /// Account frozen due to chargeback. No further transactions accepted.
     pub locked: bool,
+
+    /// Sum of all deposits ever credited.
+    #[serde(skip)]
+    total_deposited: Decimal4,
+
+    /// Sum of all withdrawals ever debited.
+    #[serde(skip)]
+    total_withdrawn: Decimal4,
+
+    /// Sum of all chargebacks ever applied.
+    #[serde(skip)]
+    total_charged_back: Decimal4,
+
+    /// Part of the balance the account was restored with that its lifetime
+    /// totals do not explain.
+    #[serde(skip)]
+    opening_balance: Decimal4,
+
+    /// Engine logical clock value when a transaction last changed this
+    /// account; zero if none has.
+    #[serde(skip)]
+    pub last_modified_at: u64,
+
+    /// IDs of transactions currently under dispute, kept sorted.
+    #[serde(skip)]
+    held_tx_ids: Vec<u32>,
+
+    /// State-change events in the order they were applied.
+    #[cfg(feature = "audit_log")]
+    #[serde(skip)]
+    audit_log: Vec<AccountEvent>,
+
+    /// Processing row of each entry in `audit_log`.
+    #[cfg(feature = "audit_log")]
+    #[serde(skip)]
+    audit_rows: Vec<usize>,
+}
+
+/// A state change applied to a [`ClientAccount`], recorded when the
+/// `audit_log` feature is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountEvent {
+    /// Funds credited by a deposit.
+    Deposited { tx_id: u32, amount: Decimal4 },
+
+    /// Funds debited by a withdrawal.
+    Withdrew { tx_id: u32, amount: Decimal4 },
+
+    /// A deposit was disputed and its funds held.
+    DisputeOpened { tx_id: u32 },
+
+    /// A dispute was resolved and its funds released.
+    DisputeResolved { tx_id: u32 },
+
+    /// Held funds were removed by a chargeback.
+    ChargebackApplied { tx_id: u32, amount: Decimal4 },
+
+    /// The account was frozen.
+    AccountLocked,
+
+    /// An administrator reverted a chargeback: the funds are held again and
+    /// the account is unlocked.
+    ChargebackReverted { tx_id: u32, amount: Decimal4 },
+}
+
+impl AccountEvent {
+    /// Returns a short snake_case name for the event, as used in exports.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            AccountEvent::Deposited { .. } => "deposited",
+            AccountEvent::Withdrew { .. } => "withdrew",
+            AccountEvent::DisputeOpened { .. } => "dispute_opened",
+            AccountEvent::DisputeResolved { .. } => "dispute_resolved",
+            AccountEvent::ChargebackApplied { .. } => "chargeback_applied",
+            AccountEvent::AccountLocked => "account_locked",
+            AccountEvent::ChargebackReverted { .. } => "chargeback_reverted",
+        }
+    }
+
+    /// Returns the transaction that caused the event, if any.
+    pub fn tx_id(&self) -> Option<u32> {
+        match *self {
+            AccountEvent::Deposited { tx_id, .. }
+            | AccountEvent::Withdrew { tx_id, .. }
+            | AccountEvent::DisputeOpened { tx_id }
+            | AccountEvent::DisputeResolved { tx_id }
+            | AccountEvent::ChargebackApplied { tx_id, .. }
+            | AccountEvent::ChargebackReverted { tx_id, .. } => Some(tx_id),
+            AccountEvent::AccountLocked => None,
+        }
+    }
+
+    /// Returns the amount moved by the event, if any.
+    pub fn amount(&self) -> Option<Decimal4> {
+        match *self {
+            AccountEvent::Deposited { amount, .. }
+            | AccountEvent::Withdrew { amount, .. }
+            | AccountEvent::ChargebackApplied { amount, .. }
+            | AccountEvent::ChargebackReverted { amount, .. } => Some(amount),
+            _ => None,
+        }
+    }
 }
 
 impl ClientAccount {
@@ -46,6 +156,114 @@ impl ClientAccount {
             held: Decimal4::ZERO,
             total: Decimal4::ZERO,
             locked: false,
+            total_deposited: Decimal4::ZERO,
+            total_withdrawn: Decimal4::ZERO,
+            total_charged_back: Decimal4::ZERO,
+            opening_balance: Decimal4::ZERO,
+            last_modified_at: 0,
+            held_tx_ids: Vec::new(),
+            #[cfg(feature = "audit_log")]
+            audit_log: Vec::new(),
+            #[cfg(feature = "audit_log")]
+            audit_rows: Vec::new(),
+        }
+    }
+
+    /// Returns the events applied to this account, oldest first.
+    #[cfg(feature = "audit_log")]
+    pub fn audit_log(&self) -> &[AccountEvent] {
+        &self.audit_log
+    }
+
+    /// Returns each audit log entry paired with the row that produced it.
+    #[cfg(feature = "audit_log")]
+    pub(crate) fn audit_entries(&self) -> impl Iterator<Item = (usize, &AccountEvent)> {
+        self.audit_rows.iter().copied().zip(&self.audit_log)
+    }
+
+    /// Appends an event produced at processing row `row` to the audit log.
+    /// A no-op without the `audit_log` feature.
+    #[cfg_attr(not(feature = "audit_log"), allow(unused_variables))]
+    pub(crate) fn record_event(&mut self, row: usize, event: AccountEvent) {
+        #[cfg(feature = "audit_log")]
+        {
+            self.audit_log.push(event);
+            self.audit_rows.push(row);
+        }
+    }
+
+    /// Returns `true` if a deposit would be accepted (the account is not locked).
+    pub fn can_deposit(&self) -> bool {
+        !self.locked
+    }
+
+    /// Returns `true` if withdrawing `amount` would be accepted.
+    ///
+    /// Returns `false` if the account is locked or `available < amount`.
+    pub fn can_withdraw(&self, amount: Decimal4) -> bool {
+        !self.locked && self.available >= amount
+    }
+
+    /// Returns the sorted IDs of transactions whose funds are currently held.
+    pub fn held_transaction_ids(&self) -> &[u32] {
+        &self.held_tx_ids
+    }
+
+    /// Returns the sum of all deposits ever credited.
+    pub fn total_deposited(&self) -> Decimal4 {
+        self.total_deposited
+    }
+
+    /// Returns the sum of all withdrawals ever debited.
+    pub fn total_withdrawn(&self) -> Decimal4 {
+        self.total_withdrawn
+    }
+
+    /// Returns the sum of all chargebacks ever applied.
+    pub fn total_charged_back(&self) -> Decimal4 {
+        self.total_charged_back
+    }
+
+    /// Returns the balance the account was restored with, net of its
+    /// lifetime totals; zero for accounts built from transactions alone.
+    pub fn opening_balance(&self) -> Decimal4 {
+        self.opening_balance
+    }
+
+    /// Records the part of `total` not explained by the lifetime totals as
+    /// the opening balance, so the extended invariant holds for a restored
+    /// account.
+    ///
+    /// Returns `false` without changing anything if the difference overflows.
+    pub(crate) fn capture_opening_balance(&mut self) -> bool {
+        match self.net_flows().and_then(|net| self.total.checked_sub(net)) {
+            Some(opening_balance) => {
+                self.opening_balance = opening_balance;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `total_deposited - total_withdrawn - total_charged_back`, or
+    /// `None` if it overflows.
+    fn net_flows(&self) -> Option<Decimal4> {
+        self.total_deposited
+            .checked_sub(self.total_withdrawn)
+            .and_then(|net| net.checked_sub(self.total_charged_back))
+    }
+
+    /// Records that `tx_id` is under dispute.
+    pub(crate) fn add_held_tx(&mut self, tx_id: u32) {
+        if let Err(pos) = self.held_tx_ids.binary_search(&tx_id) {
+            self.held_tx_ids.insert(pos, tx_id);
+        }
+    }
+
+    /// Records that `tx_id` is no longer under dispute.
+    pub(crate) fn remove_held_tx(&mut self, tx_id: u32) {
+        if let Ok(pos) = self.held_tx_ids.binary_search(&tx_id) {
+            self.held_tx_ids.remove(pos);
         }
     }
 
@@ -57,15 +275,25 @@ impl ClientAccount {
     /// Deposits funds into the account.
     ///
     /// Increases `available` and `total` by the given amount.
-    /// Returns `false` if the account is locked.
+    /// Returns `false` if the account is locked or a balance would overflow.
     pub fn deposit(&mut self, amount: Decimal4) -> bool {
-        if self.locked {
+        if !self.can_deposit() {
             return false;
         }
 
-        self.available += amount;
-        self.total += amount;
-        true
+        match (
+            self.available.checked_add(amount),
+            self.total.checked_add(amount),
+            self.total_deposited.checked_add(amount),
+        ) {
+            (Some(available), Some(total), Some(total_deposited)) => {
+                self.available = available;
+                self.total = total;
+                self.total_deposited = total_deposited;
+                true
+            }
+            _ => false,
+        }
     }
 
     /// Withdraws funds from the account.
@@ -73,18 +301,25 @@ impl ClientAccount {
     /// Returns `true` if the withdrawal succeeded, `false` if:
     /// - Account is locked
     /// - Insufficient available funds (`available < amount`)
+    /// - A balance would overflow
     pub fn withdraw(&mut self, amount: Decimal4) -> bool {
-        if self.locked {
+        if !self.can_withdraw(amount) {
             return false;
         }
 
-        if self.available < amount {
-            return false;
+        match (
+            self.available.checked_sub(amount),
+            self.total.checked_sub(amount),
+            self.total_withdrawn.checked_add(amount),
+        ) {
+            (Some(available), Some(total), Some(total_withdrawn)) => {
+                self.available = available;
+                self.total = total;
+                self.total_withdrawn = total_withdrawn;
+                true
+            }
+            _ => false,
         }
-
-        self.available -= amount;
-        self.total -= amount;
-        true
     }
 
     /// Holds funds for a dispute.
@@ -94,44 +329,157 @@ impl ClientAccount {
     /// Note: `available` may become negative if the client has withdrawn funds
     /// after the disputed deposit. This is expected behavior.
     ///
-    /// Returns `false` if the account is locked.
+    /// Returns `false` if the account is locked or a balance would overflow.
     pub fn hold(&mut self, amount: Decimal4) -> bool {
         if self.locked {
             return false;
         }
 
-        self.available -= amount;
-        self.held += amount;
-        true
+        match (
+            self.available.checked_sub(amount),
+            self.held.checked_add(amount),
+        ) {
+            (Some(available), Some(held)) => {
+                self.available = available;
+                self.held = held;
+                true
+            }
+            _ => false,
+        }
     }
 
     /// Releases held funds back to available (resolves a dispute).
     ///
     /// Moves `amount` from `held` back to `available`. The `total` remains unchanged.
-    /// Returns `false` if the account is locked.
+    /// Returns `false` if the account is locked or a balance would overflow.
     pub fn release(&mut self, amount: Decimal4) -> bool {
         if self.locked {
             return false;
         }
+        self.release_ignoring_lock(amount)
+    }
 
-        self.held -= amount;
-        self.available += amount;
-        true
+    /// Like [`release`](Self::release), but also releases funds held on a
+    /// locked account. Returns `false` if a balance would overflow.
+    pub(crate) fn release_ignoring_lock(&mut self, amount: Decimal4) -> bool {
+        match (
+            self.held.checked_sub(amount),
+            self.available.checked_add(amount),
+        ) {
+            (Some(held), Some(available)) => {
+                self.held = held;
+                self.available = available;
+                true
+            }
+            _ => false,
+        }
     }
 
     /// Processes a chargeback.
     ///
     /// Removes `amount` from `held` and `total`, then locks the account.
-    /// Returns `false` if the account is already locked.
+    /// Returns `false` if the account is already locked or a balance would overflow.
     pub fn chargeback(&mut self, amount: Decimal4) -> bool {
         if self.locked {
             return false;
         }
 
-        self.held -= amount;
-        self.total -= amount;
-        self.locked = true;
-        true
+        match (
+            self.held.checked_sub(amount),
+            self.total.checked_sub(amount),
+            self.total_charged_back.checked_add(amount),
+        ) {
+            (Some(held), Some(total), Some(total_charged_back)) => {
+                self.held = held;
+                self.total = total;
+                self.total_charged_back = total_charged_back;
+                self.locked = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reverses a chargeback of `amount`, e.g. one applied to the wrong account.
+    ///
+    /// Adds `amount` back to `held` and `total`, removes it from the lifetime
+    /// charged-back total and unlocks the account. This bypasses the normal
+    /// rule that locked accounts are frozen and is meant for administrative
+    /// correction only.
+    ///
+    /// Returns `false` without changing anything if the account is not locked,
+    /// `amount` exceeds the charged-back total, or a balance would overflow.
+    pub fn revert_chargeback(&mut self, amount: Decimal4) -> bool {
+        if !self.locked || amount > self.total_charged_back {
+            return false;
+        }
+
+        match (
+            self.held.checked_add(amount),
+            self.total.checked_add(amount),
+            self.total_charged_back.checked_sub(amount),
+        ) {
+            (Some(held), Some(total), Some(total_charged_back)) => {
+                self.held = held;
+                self.total = total;
+                self.total_charged_back = total_charged_back;
+                self.locked = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds the balances, lifetime totals, opening balance and held
+    /// transaction IDs of `other` to this account, and appends its audit log
+    /// after this account's entries.
+    ///
+    /// Returns `false` without changing anything if a balance would overflow.
+    pub(crate) fn absorb(&mut self, other: &ClientAccount) -> bool {
+        match (
+            self.available.checked_add(other.available),
+            self.held.checked_add(other.held),
+            self.total.checked_add(other.total),
+            self.total_deposited.checked_add(other.total_deposited),
+            self.total_withdrawn.checked_add(other.total_withdrawn),
+            self.total_charged_back
+                .checked_add(other.total_charged_back),
+            self.opening_balance.checked_add(other.opening_balance),
+        ) {
+            (
+                Some(available),
+                Some(held),
+                Some(total),
+                Some(total_deposited),
+                Some(total_withdrawn),
+                Some(total_charged_back),
+                Some(opening_balance),
+            ) => {
+                self.available = available;
+                self.held = held;
+                self.total = total;
+                self.total_deposited = total_deposited;
+                self.total_withdrawn = total_withdrawn;
+                self.total_charged_back = total_charged_back;
+                self.opening_balance = opening_balance;
+                for &tx_id in &other.held_tx_ids {
+                    self.add_held_tx(tx_id);
+                }
+                #[cfg(feature = "audit_log")]
+                {
+                    self.audit_log.extend_from_slice(&other.audit_log);
+                    self.audit_rows.extend_from_slice(&other.audit_rows);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if `total == available + held`, treating a sum that
+    /// would overflow as a mismatch.
+    pub(crate) fn balances_consistent(&self) -> bool {
+        self.available.checked_add(self.held) == Some(self.total)
     }
 
     /// Verifies the invariant: `total == available + held`.
@@ -139,17 +487,144 @@ impl ClientAccount {
     pub fn check_invariant(&self) -> bool {
         self.total == self.available + self.held
     }
+
+    /// Verifies that the lifetime flows explain the balance:
+    /// `total == opening_balance + total_deposited - total_withdrawn - charged back amounts`.
+    pub fn check_extended_invariant(&self) -> bool {
+        self.net_flows()
+            .and_then(|net| net.checked_add(self.opening_balance))
+            == Some(self.total)
+    }
+}
+
+impl fmt::Display for ClientAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "client#{}: available={} held={} total={} locked={}",
+            self.client, self.available, self.held, self.total, self.locked
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use rust_decimal::Decimal;
     use std::str::FromStr;
 
     fn dec(s: &str) -> Decimal4 {
         Decimal4::from_str(s).unwrap()
     }
 
+    #[test]
+    fn test_display() {
+        let mut account = ClientAccount::new(1);
+        account.deposit(dec("10.0"));
+        assert_eq!(
+            account.to_string(),
+            "client#1: available=10.0000 held=0.0000 total=10.0000 locked=false"
+        );
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut locked = ClientAccount::new(7);
+        locked.deposit(dec("12.3456"));
+        locked.hold(dec("2.0"));
+        locked.chargeback(dec("2.0"));
+        let mut negative = ClientAccount::new(65535);
+        negative.deposit(dec("1.0"));
+        negative.withdraw(dec("0.5"));
+        negative.hold(dec("1.0"));
+        let originals = [ClientAccount::new(0), locked, negative];
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for account in &originals {
+            writer.serialize(account).unwrap();
+        }
+        let written = writer.into_inner().unwrap();
+
+        let mut reader = csv::Reader::from_reader(written.as_slice());
+        let parsed: Vec<ClientAccount> = reader.deserialize().map(|r| r.unwrap()).collect();
+        assert_eq!(parsed.len(), originals.len());
+        for (parsed, original) in parsed.iter().zip(&originals) {
+            assert_eq!(parsed.client, original.client);
+            assert_eq!(parsed.available, original.available);
+            assert_eq!(parsed.held, original.held);
+            assert_eq!(parsed.total, original.total);
+            assert_eq!(parsed.locked, original.locked);
+            assert!(parsed.held_transaction_ids().is_empty());
+        }
+
+        let mut rewriter = csv::Writer::from_writer(Vec::new());
+        for account in &parsed {
+            rewriter.serialize(account).unwrap();
+        }
+        assert_eq!(rewriter.into_inner().unwrap(), written);
+    }
+
+    #[test]
+    fn test_lifetime_totals() {
+        let mut account = ClientAccount::new(1);
+        account.deposit(dec("10.0"));
+        account.deposit(dec("5.0"));
+        account.withdraw(dec("3.0"));
+        account.withdraw(dec("100.0"));
+        account.hold(dec("5.0"));
+        account.chargeback(dec("5.0"));
+
+        assert_eq!(account.total_deposited(), dec("15.0"));
+        assert_eq!(account.total_withdrawn(), dec("3.0"));
+        assert_eq!(account.total, dec("7.0"));
+        assert!(account.check_extended_invariant());
+
+        account.total = dec("8.0");
+        assert!(!account.check_extended_invariant());
+
+        account.total_deposited = Decimal4::new(Decimal::MAX);
+        account.total_withdrawn = -account.total_deposited;
+        assert!(!account.check_extended_invariant());
+    }
+
+    #[test]
+    fn test_opening_balance() {
+        let mut account = ClientAccount::new(1);
+        account.available = dec("100.0");
+        account.total = dec("100.0");
+        assert!(!account.check_extended_invariant());
+
+        assert!(account.capture_opening_balance());
+        assert_eq!(account.opening_balance(), dec("100.0"));
+        assert!(account.check_extended_invariant());
+
+        account.deposit(dec("20.0"));
+        account.withdraw(dec("50.0"));
+        assert!(account.check_extended_invariant());
+        assert!(account.capture_opening_balance());
+        assert_eq!(account.opening_balance(), dec("100.0"));
+    }
+
+    #[test]
+    fn test_revert_chargeback() {
+        let mut account = ClientAccount::new(1);
+        account.deposit(dec("10.0"));
+        assert!(!account.revert_chargeback(dec("1.0")));
+
+        account.hold(dec("4.0"));
+        account.chargeback(dec("4.0"));
+        assert!(!account.revert_chargeback(dec("5.0")));
+
+        assert!(account.revert_chargeback(dec("4.0")));
+        assert!(!account.locked);
+        assert_eq!(account.available, dec("6.0"));
+        assert_eq!(account.held, dec("4.0"));
+        assert_eq!(account.total, dec("10.0"));
+        assert_eq!(account.total_charged_back(), Decimal4::ZERO);
+        assert!(account.check_extended_invariant());
+    }
+
     #[test]
     fn test_new_account_has_zero_balances() {
         let account = ClientAccount::new(1);
@@ -192,6 +667,22 @@ mod tests {
         assert_eq!(account.total.to_string(), "10.0000");
     }
 
+    #[test]
+    fn test_can_withdraw_and_deposit_predicates() {
+        let mut account = ClientAccount::new(1);
+        account.deposit(dec("10.0"));
+
+        assert!(account.can_deposit());
+        assert!(account.can_withdraw(dec("10.0")));
+        assert!(!account.can_withdraw(dec("10.0001")));
+        assert_eq!(account.available.to_string(), "10.0000");
+
+        account.hold(dec("10.0"));
+        account.chargeback(dec("10.0"));
+        assert!(!account.can_deposit());
+        assert!(!account.can_withdraw(Decimal4::ZERO));
+    }
+
     #[test]
     fn test_hold_and_release_cycle() {
         let mut account = ClientAccount::new(1);
@@ -242,4 +733,83 @@ mod tests {
         assert_eq!(account.available.to_string(), "5.0000");
         assert_eq!(account.total.to_string(), "5.0000");
     }
+
+    #[test]
+    fn test_held_tx_ids_stay_sorted() {
+        let mut account = ClientAccount::new(1);
+        account.add_held_tx(7);
+        account.add_held_tx(3);
+        account.add_held_tx(5);
+        account.add_held_tx(3);
+        assert_eq!(account.held_transaction_ids(), &[3, 5, 7]);
+
+        account.remove_held_tx(5);
+        account.remove_held_tx(9);
+        assert_eq!(account.held_transaction_ids(), &[3, 7]);
+    }
+
+    #[test]
+    fn test_deposit_overflow_is_rejected() {
+        let mut account = ClientAccount::new(1);
+        assert!(account.deposit(Decimal4::MAX_REPRESENTABLE));
+        assert!(!account.deposit(dec("1.0")));
+
+        assert_eq!(account.available, Decimal4::MAX_REPRESENTABLE);
+        assert_eq!(account.total, Decimal4::MAX_REPRESENTABLE);
+        assert!(account.check_invariant());
+    }
+
+    #[test]
+    fn test_hold_overflow_is_rejected() {
+        let mut account = ClientAccount::new(1);
+        account.available = -Decimal4::MAX_REPRESENTABLE;
+        account.held = Decimal4::MAX_REPRESENTABLE;
+
+        assert!(!account.hold(dec("1.0")));
+        assert!(!account.release(-dec("1.0")));
+        assert_eq!(account.held, Decimal4::MAX_REPRESENTABLE);
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    enum Op {
+        Deposit(Decimal4),
+        Withdraw(Decimal4),
+        Hold(Decimal4),
+        Release(Decimal4),
+        Chargeback(Decimal4),
+    }
+
+    /// Amounts up to one billion with 4 decimal places; sequences of these
+    /// cannot overflow a balance.
+    fn amount() -> impl Strategy<Value = Decimal4> {
+        (0i64..=10_000_000_000_000).prop_map(|units| Decimal4::new(Decimal::new(units, 4)))
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            4 => amount().prop_map(Op::Deposit),
+            3 => amount().prop_map(Op::Withdraw),
+            2 => amount().prop_map(Op::Hold),
+            2 => amount().prop_map(Op::Release),
+            1 => amount().prop_map(Op::Chargeback),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_operations_preserve_invariants(ops in prop::collection::vec(op(), 1..50)) {
+            let mut account = ClientAccount::new(1);
+            for op in ops {
+                match op {
+                    Op::Deposit(amount) => account.deposit(amount),
+                    Op::Withdraw(amount) => account.withdraw(amount),
+                    Op::Hold(amount) => account.hold(amount),
+                    Op::Release(amount) => account.release(amount),
+                    Op::Chargeback(amount) => account.chargeback(amount),
+                };
+                prop_assert!(account.check_invariant(), "{:?} broke {}", op, account);
+                prop_assert!(account.check_extended_invariant(), "{:?} broke {}", op, account);
+            }
+        }
+    }
 }
@@ -0,0 +1,163 @@
+//! Builder for configuring a [`PaymentsEngine`].
+
+use crate::config::EngineConfig;
+use crate::decimal::RoundingMode;
+use crate::engine::PaymentsEngine;
+use crate::hooks::{EngineHooks, NoopHooks};
+
+/// Chainable builder for [`PaymentsEngine`].
+///
+/// The default builder produces the same engine as [`PaymentsEngine::new`].
+///
+/// # Examples
+///
+/// ```
+/// use payments_engine::PaymentsEngine;
+///
+/// let engine = PaymentsEngine::builder()
+///     .reject_zero_amounts(true)
+///     .max_clients(10_000)
+///     .build();
+/// assert!(engine.config().reject_zero_amounts);
+/// ```
+pub struct PaymentsEngineBuilder {
+    config: EngineConfig,
+    hooks: Box<dyn EngineHooks>,
+}
+
+impl PaymentsEngineBuilder {
+    /// Creates a builder with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the whole configuration.
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets [`EngineConfig::rounding_mode`].
+    pub fn rounding_mode(mut self, mode: RoundingMode) -> Self {
+        self.config.rounding_mode = mode;
+        self
+    }
+
+    /// Sets [`EngineConfig::progress_interval`].
+    pub fn progress_interval(mut self, rows: usize) -> Self {
+        self.config.progress_interval = rows;
+        self
+    }
+
+    /// Sets [`EngineConfig::max_transactions_per_client`].
+    pub fn max_transactions_per_client(mut self, max: usize) -> Self {
+        self.config.max_transactions_per_client = Some(max);
+        self
+    }
+
+    /// Sets [`EngineConfig::reject_zero_amounts`].
+    pub fn reject_zero_amounts(mut self, reject: bool) -> Self {
+        self.config.reject_zero_amounts = reject;
+        self
+    }
+
+    /// Sets [`EngineConfig::strict_duplicate_tx_ids`].
+    pub fn strict_duplicate_tx_ids(mut self, strict: bool) -> Self {
+        self.config.strict_duplicate_tx_ids = strict;
+        self
+    }
+
+    /// Sets [`EngineConfig::allow_negative_available`].
+    pub fn allow_negative_available(mut self, allow: bool) -> Self {
+        self.config.allow_negative_available = allow;
+        self
+    }
+
+    /// Sets [`EngineConfig::max_clients`].
+    pub fn max_clients(mut self, max: usize) -> Self {
+        self.config.max_clients = Some(max);
+        self
+    }
+
+    /// Sets [`EngineConfig::max_transactions`].
+    pub fn max_transactions(mut self, max: usize) -> Self {
+        self.config.max_transactions = Some(max);
+        self
+    }
+
+    /// Sets [`EngineConfig::strict_business_rules`].
+    pub fn strict_business_rules(mut self, strict: bool) -> Self {
+        self.config.strict_business_rules = strict;
+        self
+    }
+
+    /// Sets [`EngineConfig::partial_withdrawal_allowed`].
+    pub fn partial_withdrawal_allowed(mut self, allowed: bool) -> Self {
+        self.config.partial_withdrawal_allowed = allowed;
+        self
+    }
+
+    /// Sets the hooks notified of account changes.
+    pub fn hooks(mut self, hooks: impl EngineHooks + 'static) -> Self {
+        self.hooks = Box::new(hooks);
+        self
+    }
+
+    /// Builds the engine.
+    pub fn build(self) -> PaymentsEngine {
+        PaymentsEngine::with_config_and_hooks(self.config, self.hooks)
+    }
+}
+
+impl Default for PaymentsEngineBuilder {
+    fn default() -> Self {
+        PaymentsEngineBuilder {
+            config: EngineConfig::default(),
+            hooks: Box::new(NoopHooks),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_new() {
+        let engine = PaymentsEngineBuilder::default().build();
+        assert_eq!(engine.config(), PaymentsEngine::new().config());
+    }
+
+    #[test]
+    fn test_chained_setters() {
+        let engine = PaymentsEngineBuilder::new()
+            .rounding_mode(RoundingMode::Truncate)
+            .progress_interval(10)
+            .max_transactions_per_client(5)
+            .reject_zero_amounts(true)
+            .strict_duplicate_tx_ids(true)
+            .allow_negative_available(false)
+            .max_clients(100)
+            .max_transactions(1000)
+            .strict_business_rules(true)
+            .partial_withdrawal_allowed(true)
+            .hooks(NoopHooks)
+            .build();
+
+        assert_eq!(
+            engine.config(),
+            &EngineConfig {
+                rounding_mode: RoundingMode::Truncate,
+                progress_interval: 10,
+                max_transactions_per_client: Some(5),
+                reject_zero_amounts: true,
+                strict_duplicate_tx_ids: true,
+                allow_negative_available: false,
+                max_clients: Some(100),
+                max_transactions: Some(1000),
+                strict_business_rules: true,
+                partial_withdrawal_allowed: true,
+            }
+        );
+    }
+}
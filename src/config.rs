@@ -0,0 +1,94 @@
+//! Engine configuration.
+//!
+//! Groups the tunable behaviour of [`PaymentsEngine`](crate::PaymentsEngine)
+//! so new options can be added without changing constructor signatures.
+
+use crate::decimal::RoundingMode;
+
+/// Configuration options for the payments engine.
+///
+/// # Examples
+///
+/// ```
+/// use payments_engine::{EngineConfig, PaymentsEngine, RoundingMode};
+///
+/// let config = EngineConfig {
+///     rounding_mode: RoundingMode::Truncate,
+///     ..EngineConfig::default()
+/// };
+/// let engine = PaymentsEngine::with_config(config);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Rounding applied when input amounts have more than 4 decimal places.
+    pub rounding_mode: RoundingMode,
+
+    /// Number of rows between progress callbacks in
+    /// [`process_csv_with_progress`](crate::PaymentsEngine::process_csv_with_progress).
+    /// Zero disables callbacks. Defaults to 1000.
+    pub progress_interval: usize,
+
+    /// Maximum number of stored deposits per client. Deposits beyond the limit
+    /// are skipped. `None` (the default) means unlimited.
+    pub max_transactions_per_client: Option<usize>,
+
+    /// Skip deposits and withdrawals with a zero amount. Defaults to `false`.
+    pub reject_zero_amounts: bool,
+
+    /// Treat a repeated transaction ID as an error
+    /// ([`EngineError::DuplicateTxId`](crate::EngineError::DuplicateTxId))
+    /// instead of skipping the row. Defaults to `false`.
+    pub strict_duplicate_tx_ids: bool,
+
+    /// Allow a dispute to hold more than the available balance, leaving
+    /// `available` negative. When `false` such disputes are skipped.
+    /// Defaults to `true`.
+    pub allow_negative_available: bool,
+
+    /// Maximum number of client accounts. Deposits and withdrawals that would
+    /// open a new account beyond the limit are skipped. `None` (the default)
+    /// means unlimited.
+    pub max_clients: Option<usize>,
+
+    /// Maximum number of stored deposits across all clients. Deposits beyond
+    /// the limit are skipped. `None` (the default) means unlimited.
+    pub max_transactions: Option<usize>,
+
+    /// Report withdrawals with insufficient funds, transactions for locked
+    /// accounts and disputes of another client's transaction as errors
+    /// ([`EngineError::InsufficientFunds`], [`EngineError::AccountLocked`],
+    /// [`EngineError::TransactionClientMismatch`]) instead of silently
+    /// skipping them, and reject disputes, resolves and chargebacks that carry
+    /// an amount instead of ignoring it. Lenient processing logs these errors
+    /// and continues; strict processing returns them. Defaults to `false`.
+    ///
+    /// [`EngineError::InsufficientFunds`]: crate::EngineError::InsufficientFunds
+    /// [`EngineError::AccountLocked`]: crate::EngineError::AccountLocked
+    /// [`EngineError::TransactionClientMismatch`]: crate::EngineError::TransactionClientMismatch
+    pub strict_business_rules: bool,
+
+    /// Let a withdrawal larger than a positive available balance withdraw the
+    /// whole balance instead of being rejected. Only the amount actually
+    /// withdrawn is recorded; the shortfall is logged as a warning and passed
+    /// to [`EngineHooks::on_partial_withdrawal`]. Defaults to `false`.
+    ///
+    /// [`EngineHooks::on_partial_withdrawal`]: crate::EngineHooks::on_partial_withdrawal
+    pub partial_withdrawal_allowed: bool,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            rounding_mode: RoundingMode::default(),
+            progress_interval: 1000,
+            max_transactions_per_client: None,
+            reject_zero_amounts: false,
+            strict_duplicate_tx_ids: false,
+            allow_negative_available: true,
+            max_clients: None,
+            max_transactions: None,
+            strict_business_rules: false,
+            partial_withdrawal_allowed: false,
+        }
+    }
+}
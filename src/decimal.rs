@@ -3,12 +3,38 @@
 //! Uses `rust_decimal` internally with scale enforcement to ensure
 //! consistent monetary calculations without floating-point errors.
 
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 use std::str::FromStr;
 
+/// Rounding strategy used when normalizing a value to 4 decimal places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Round to nearest, with ties rounded away from zero (`0.00005` -> `0.0001`).
+    #[default]
+    HalfUp,
+
+    /// Round to nearest, with ties rounded to the even digit (banker's rounding).
+    HalfEven,
+
+    /// Discard extra decimal places, rounding toward zero.
+    Truncate,
+}
+
+impl RoundingMode {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::Truncate => RoundingStrategy::ToZero,
+        }
+    }
+}
+
 /// A decimal type that maintains exactly 4 decimal places of precision.
 ///
 /// This type wraps `rust_decimal::Decimal` and ensures consistent scale
@@ -33,26 +59,238 @@ impl Decimal4 {
     /// Zero value.
     pub const ZERO: Self = Decimal4(Decimal::ZERO);
 
+    /// One (`1.0000`).
+    pub const ONE: Self = Decimal4(Decimal::from_parts(10_000, 0, 0, false, Self::SCALE));
+
+    /// One hundred (`100.0000`), for percentage calculations.
+    pub const HUNDRED: Self = Decimal4(Decimal::from_parts(1_000_000, 0, 0, false, Self::SCALE));
+
+    /// The largest value `rust_decimal` can hold at 4 decimal places.
+    pub const MAX_REPRESENTABLE: Self = Decimal4(Decimal::from_parts(
+        u32::MAX,
+        u32::MAX,
+        u32::MAX,
+        false,
+        Self::SCALE,
+    ));
+
     /// Creates a new `Decimal4` from a `Decimal`, normalizing to 4 decimal places.
+    ///
+    /// Extra decimal places are rounded using [`RoundingMode::HalfUp`].
     pub fn new(value: Decimal) -> Self {
-        let mut normalized = value;
+        Self::with_rounding(value, RoundingMode::HalfUp)
+    }
+
+    /// Creates a new `Decimal4` from a `Decimal`, rounding extra decimal places
+    /// with the given mode.
+    pub fn with_rounding(value: Decimal, mode: RoundingMode) -> Self {
+        let mut normalized = value.round_dp_with_strategy(Self::SCALE, mode.strategy());
         normalized.rescale(Self::SCALE);
         Decimal4(normalized)
     }
 
+    /// Parses a decimal string, rounding extra decimal places with the given mode.
+    ///
+    /// Surrounding whitespace is ignored. Fails if the value is too large to
+    /// be held at 4 decimal places.
+    pub fn from_str_with_rounding(
+        s: &str,
+        mode: RoundingMode,
+    ) -> std::result::Result<Self, rust_decimal::Error> {
+        let value = Decimal4::with_rounding(Decimal::from_str(s.trim())?, mode);
+        if value.0.scale() != Self::SCALE {
+            return Err(if value.is_negative() {
+                rust_decimal::Error::LessThanMinimumPossibleValue
+            } else {
+                rust_decimal::Error::ExceedsMaximumPossibleValue
+            });
+        }
+        Ok(value)
+    }
+
+    /// Parses a decimal string, discarding decimal places beyond the fourth
+    /// (`"1.99999"` becomes `1.9999`).
+    pub fn from_str_truncating(s: &str) -> std::result::Result<Self, rust_decimal::Error> {
+        Self::from_str_with_rounding(s, RoundingMode::Truncate)
+    }
+
+    /// Parses a decimal string, rounding half away from zero to 4 decimal
+    /// places (`"1.00005"` becomes `1.0001`).
+    pub fn from_str_rounding(s: &str) -> std::result::Result<Self, rust_decimal::Error> {
+        Self::from_str_with_rounding(s, RoundingMode::HalfUp)
+    }
+
     /// Returns `true` if this value is zero.
     pub fn is_zero(&self) -> bool {
         self.0.is_zero()
     }
+
+    /// Returns `true` if this value is strictly less than zero.
+    pub fn is_negative(&self) -> bool {
+        self.0.is_sign_negative() && !self.0.is_zero()
+    }
+
+    /// Returns what percentage `self` is of `total`, rounded to 4 decimal places.
+    ///
+    /// The ratio is computed at full precision before rounding, so
+    /// `1.percentage_of(3)` is `33.3333`. Returns `None` if `total` is zero
+    /// or the result cannot be represented.
+    pub fn percentage_of(&self, total: Decimal4) -> Option<Decimal4> {
+        if total.is_zero() {
+            return None;
+        }
+        self.0
+            .checked_mul(Self::HUNDRED.0)?
+            .checked_div(total.0)
+            .map(Decimal4::new)
+    }
+
+    /// Returns the smaller of `a` and `b`.
+    ///
+    /// Equivalent to `std::cmp::min(a, b)`; method syntax such as
+    /// `requested.min(available)` uses the same [`Ord`] implementation.
+    pub fn min(a: Decimal4, b: Decimal4) -> Decimal4 {
+        std::cmp::min(a, b)
+    }
+
+    /// Returns the larger of `a` and `b`.
+    ///
+    /// Equivalent to `std::cmp::max(a, b)`.
+    pub fn max(a: Decimal4, b: Decimal4) -> Decimal4 {
+        std::cmp::max(a, b)
+    }
+
+    /// Restricts `value` to the range `min..=max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min > max`, like [`Ord::clamp`].
+    pub fn clamp(value: Decimal4, min: Decimal4, max: Decimal4) -> Decimal4 {
+        Ord::clamp(value, min, max)
+    }
+
+    /// Checked addition. Returns `None` if the result cannot be represented
+    /// at 4 decimal places (i.e. it would exceed [`Decimal4::MAX_REPRESENTABLE`]).
+    pub fn checked_add(self, rhs: Decimal4) -> Option<Decimal4> {
+        self.0.checked_add(rhs.0).and_then(Self::checked_new)
+    }
+
+    /// Checked subtraction. Returns `None` if the result cannot be represented
+    /// at 4 decimal places.
+    pub fn checked_sub(self, rhs: Decimal4) -> Option<Decimal4> {
+        self.0.checked_sub(rhs.0).and_then(Self::checked_new)
+    }
+
+    /// Saturating addition. Returns [`Decimal4::MAX_REPRESENTABLE`] (or its
+    /// negation) instead of overflowing.
+    pub fn saturating_add(self, rhs: Decimal4) -> Decimal4 {
+        self.checked_add(rhs).unwrap_or(if rhs.is_negative() {
+            -Self::MAX_REPRESENTABLE
+        } else {
+            Self::MAX_REPRESENTABLE
+        })
+    }
+
+    /// Normalizes to 4 decimal places, failing if the value is too large to
+    /// keep that scale.
+    fn checked_new(value: Decimal) -> Option<Decimal4> {
+        let normalized = Decimal4::new(value);
+        (normalized.0.scale() == Self::SCALE).then_some(normalized)
+    }
+
+    /// Creates a `Decimal4` from an integer count of minor units.
+    ///
+    /// The value is `units / 10^scale`, so `from_minor_units(1050, 2)` is `10.5000`.
+    /// Values with more than 4 decimal places are rounded to 4.
+    /// Returns an error if `scale` exceeds the maximum supported by `rust_decimal` (28).
+    pub fn from_minor_units(
+        units: i64,
+        scale: u32,
+    ) -> std::result::Result<Self, rust_decimal::Error> {
+        Decimal::try_new(units, scale).map(Decimal4::new)
+    }
+
+    /// Converts this value to an integer count of minor units at the given scale.
+    ///
+    /// Exact for `scale == 4`. For smaller scales, extra decimal places are
+    /// truncated toward zero. Results outside the `i64` range saturate.
+    pub fn to_minor_units(&self, scale: u32) -> i64 {
+        let mut truncated = self.0.trunc_with_scale(scale);
+        truncated.rescale(scale);
+        i64::try_from(truncated.mantissa()).unwrap_or(if truncated.is_sign_negative() {
+            i64::MIN
+        } else {
+            i64::MAX
+        })
+    }
+}
+
+impl From<Decimal4> for Decimal {
+    fn from(value: Decimal4) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<Decimal> for Decimal4 {
+    type Error = rust_decimal::Error;
+
+    /// Converts a `Decimal` to 4 decimal places without rounding.
+    ///
+    /// Fails if the value has significant digits beyond the fourth decimal
+    /// place, or is too large to be held at 4 decimal places.
+    fn try_from(value: Decimal) -> std::result::Result<Self, Self::Error> {
+        if value.normalize().scale() > Self::SCALE {
+            return Err(rust_decimal::Error::Underflow);
+        }
+        Self::checked_new(value).ok_or(if value.is_sign_negative() {
+            rust_decimal::Error::LessThanMinimumPossibleValue
+        } else {
+            rust_decimal::Error::ExceedsMaximumPossibleValue
+        })
+    }
+}
+
+impl From<u32> for Decimal4 {
+    fn from(value: u32) -> Self {
+        Decimal4::new(Decimal::from(value))
+    }
+}
+
+impl From<u16> for Decimal4 {
+    fn from(value: u16) -> Self {
+        Decimal4::new(Decimal::from(value))
+    }
+}
+
+impl TryFrom<f64> for Decimal4 {
+    type Error = rust_decimal::Error;
+
+    /// Converts a float, rounding to 4 decimal places.
+    ///
+    /// Fails for NaN, infinities, and values outside the `Decimal` range.
+    fn try_from(value: f64) -> std::result::Result<Self, Self::Error> {
+        Decimal::try_from(value).map(Decimal4::new)
+    }
 }
 
 impl FromStr for Decimal4 {
     type Err = rust_decimal::Error;
 
+    /// Parses a decimal string, rounding extra decimal places with
+    /// [`RoundingMode::HalfUp`].
+    ///
+    /// Use [`Decimal4::from_str_truncating`] or [`Decimal4::from_str_rounding`]
+    /// when input must follow a specific rounding rule.
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        let trimmed = s.trim();
-        let decimal = Decimal::from_str(trimmed)?;
-        Ok(Decimal4::new(decimal))
+        Self::from_str_with_rounding(s, RoundingMode::HalfUp)
+    }
+}
+
+/// Hashes the normalized inner value, so values that compare equal hash
+/// equally even if their scales differ (e.g. [`Decimal4::ZERO`] has scale 0).
+impl Hash for Decimal4 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.normalize().hash(state);
     }
 }
 
@@ -92,6 +330,43 @@ impl SubAssign for Decimal4 {
     }
 }
 
+impl Neg for Decimal4 {
+    type Output = Self;
+
+    /// Negates the value. Zero stays positive zero.
+    fn neg(self) -> Self::Output {
+        if self.is_zero() {
+            return Decimal4::new(Decimal::ZERO);
+        }
+        Decimal4::new(-self.0)
+    }
+}
+
+impl Mul for Decimal4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Decimal4::new(self.0 * rhs.0)
+    }
+}
+
+impl Div for Decimal4 {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Decimal4::new(self.0 / rhs.0)
+    }
+}
+
+/// Sums with [`saturating_add`](Decimal4::saturating_add), so a total that
+/// would overflow is clamped to [`MAX_REPRESENTABLE`](Decimal4::MAX_REPRESENTABLE)
+/// instead of panicking.
+impl Sum for Decimal4 {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Decimal4::ZERO, Decimal4::saturating_add)
+    }
+}
+
 impl Serialize for Decimal4 {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -114,6 +389,7 @@ impl<'de> Deserialize<'de> for Decimal4 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_from_str_normalizes_scale() {
@@ -130,6 +406,98 @@ mod tests {
         assert_eq!(d.to_string(), "2.5000");
     }
 
+    #[test]
+    fn test_from_str_truncating_and_rounding() {
+        assert_eq!(
+            Decimal4::from_str_truncating("1.99999").unwrap(),
+            Decimal4::from_str("1.9999").unwrap()
+        );
+        assert_eq!(
+            Decimal4::from_str_truncating(" -1.99999 ")
+                .unwrap()
+                .to_string(),
+            "-1.9999"
+        );
+        assert_eq!(
+            Decimal4::from_str_rounding("1.99995").unwrap().to_string(),
+            "2.0000"
+        );
+        assert_eq!(
+            Decimal4::from_str_rounding("1.00005").unwrap().to_string(),
+            "1.0001"
+        );
+        assert_eq!(Decimal4::from_str("1.00005").unwrap().to_string(), "1.0001");
+        assert!(Decimal4::from_str_truncating("abc").is_err());
+        assert!(Decimal4::from_str_rounding("").is_err());
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let tie = Decimal::from_str("1.00005").unwrap();
+        assert_eq!(
+            Decimal4::with_rounding(tie, RoundingMode::HalfUp).to_string(),
+            "1.0001"
+        );
+        assert_eq!(
+            Decimal4::with_rounding(tie, RoundingMode::HalfEven).to_string(),
+            "1.0000"
+        );
+        assert_eq!(
+            Decimal4::with_rounding(tie, RoundingMode::Truncate).to_string(),
+            "1.0000"
+        );
+
+        let above = Decimal::from_str("-2.12349").unwrap();
+        assert_eq!(
+            Decimal4::with_rounding(above, RoundingMode::HalfUp).to_string(),
+            "-2.1235"
+        );
+        assert_eq!(
+            Decimal4::with_rounding(above, RoundingMode::HalfEven).to_string(),
+            "-2.1235"
+        );
+        assert_eq!(
+            Decimal4::with_rounding(above, RoundingMode::Truncate).to_string(),
+            "-2.1234"
+        );
+
+        assert_eq!(
+            Decimal4::new(tie),
+            Decimal4::with_rounding(tie, RoundingMode::default())
+        );
+    }
+
+    #[test]
+    fn test_default_rounding_ties_away_from_zero() {
+        for (input, expected) in [
+            ("1.00005", "1.0001"),
+            ("-1.00005", "-1.0001"),
+            ("2.00015", "2.0002"),
+            ("1.00004", "1.0000"),
+        ] {
+            let value = Decimal::from_str(input).unwrap();
+            assert_eq!(Decimal4::new(value).to_string(), expected);
+            assert_eq!(Decimal4::from_str(input).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_min_max_clamp() {
+        let d = |s: &str| Decimal4::from_str(s).unwrap();
+        let (low, high) = (d("-1.5"), d("2.0001"));
+
+        assert_eq!(Decimal4::min(low, high), low);
+        assert_eq!(Decimal4::max(low, high), high);
+        assert_eq!(std::cmp::min(high, low), low);
+        assert_eq!(std::cmp::max(high, low), high);
+        assert_eq!(d("5.0").min(d("3.25")), d("3.25"));
+
+        assert_eq!(Decimal4::clamp(d("3.0"), low, high), high);
+        assert_eq!(Decimal4::clamp(d("-3.0"), low, high), low);
+        assert_eq!(Decimal4::clamp(Decimal4::ONE, low, high), Decimal4::ONE);
+        assert_eq!(Decimal4::clamp(d("3.0"), low, high).to_string(), "2.0001");
+    }
+
     #[test]
     fn test_arithmetic_preserves_scale() {
         let a = Decimal4::from_str("1.5").unwrap();
@@ -144,12 +512,222 @@ mod tests {
         assert!(Decimal4::ZERO.is_zero());
     }
 
+    #[test]
+    fn test_one_and_hundred_constants() {
+        let amount = Decimal4::from_str("12.3456").unwrap();
+        assert_eq!(amount * Decimal4::ONE, amount);
+        assert_eq!(Decimal4::ONE.to_string(), "1.0000");
+        assert_eq!(Decimal4::HUNDRED.to_string(), "100.0000");
+
+        let rate = Decimal4::from_str("2.5").unwrap();
+        let fee = Decimal4::from_str("200").unwrap() * rate / Decimal4::HUNDRED;
+        assert_eq!(fee.to_string(), "5.0000");
+    }
+
+    #[test]
+    fn test_max_representable_constant() {
+        assert_eq!(
+            Decimal4::MAX_REPRESENTABLE.to_string(),
+            "7922816251426433759354395.0335"
+        );
+        assert!(Decimal4::from_str("999999999999.9999").unwrap() < Decimal4::MAX_REPRESENTABLE);
+        assert!(Decimal4::from_str("7922816251426433759354395.0335").is_ok());
+        assert!(Decimal4::from_str("7922816251426433759354396").is_err());
+        assert!(Decimal4::from_str("-79228162514264337593543950335").is_err());
+    }
+
+    #[test]
+    fn test_neg() {
+        let amount = Decimal4::from_str("2.5").unwrap();
+        assert_eq!((-amount).to_string(), "-2.5000");
+        assert_eq!(-(-amount), amount);
+
+        assert_eq!(-Decimal4::ZERO, Decimal4::ZERO);
+        assert_eq!((-Decimal4::ZERO).to_string(), "0.0000");
+        assert!(!(-Decimal4::ZERO).is_negative());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a = Decimal4::from_str("1.5").unwrap();
+        let b = Decimal4::from_str("2.5").unwrap();
+
+        assert_eq!(a.checked_add(b).unwrap().to_string(), "4.0000");
+        assert_eq!(a.checked_sub(b).unwrap().to_string(), "-1.0000");
+    }
+
+    #[test]
+    fn test_checked_arithmetic_overflow() {
+        let max = Decimal4::MAX_REPRESENTABLE;
+        assert!(max.checked_add(Decimal4::ONE).is_none());
+        assert!(max.checked_sub(Decimal4::ONE).is_some());
+
+        let min = -max;
+        assert!(min.checked_sub(Decimal4::ONE).is_none());
+        assert!(min.checked_add(Decimal4::ONE).is_some());
+    }
+
+    #[test]
+    fn test_saturating_add() {
+        let max = Decimal4::MAX_REPRESENTABLE;
+        assert_eq!(
+            Decimal4::ONE.saturating_add(Decimal4::ONE).to_string(),
+            "2.0000"
+        );
+        assert_eq!(max.saturating_add(Decimal4::ONE), max);
+        assert_eq!((-max).saturating_add(-Decimal4::ONE), -max);
+        assert_eq!(max.saturating_add(-Decimal4::ONE), max - Decimal4::ONE);
+    }
+
+    #[test]
+    fn test_sum_saturates() {
+        let max = Decimal4::MAX_REPRESENTABLE;
+        let ones: Decimal4 = [Decimal4::ONE, Decimal4::ONE].into_iter().sum();
+        assert_eq!(ones.to_string(), "2.0000");
+        assert_eq!([max, max, max].into_iter().sum::<Decimal4>(), max);
+        assert_eq!([-max, -Decimal4::ONE].into_iter().sum::<Decimal4>(), -max);
+    }
+
+    #[test]
+    fn test_decimal_conversions() {
+        let d = Decimal::from_str("12.5").unwrap();
+        let d4 = Decimal4::try_from(d).unwrap();
+        assert_eq!(d4.to_string(), "12.5000");
+        assert_eq!(Decimal::from(d4), d);
+
+        let trailing_zeros = Decimal::from_str("1.230000").unwrap();
+        assert_eq!(
+            Decimal4::try_from(trailing_zeros).unwrap().to_string(),
+            "1.2300"
+        );
+
+        let too_precise = Decimal::from_str("1.23456").unwrap();
+        assert!(Decimal4::try_from(too_precise).is_err());
+        assert!(Decimal4::try_from(Decimal::MAX).is_err());
+    }
+
+    #[test]
+    fn test_integer_and_float_conversions() {
+        assert_eq!(Decimal4::from(42u32).to_string(), "42.0000");
+        assert_eq!(Decimal4::from(u16::MAX).to_string(), "65535.0000");
+
+        assert_eq!(Decimal4::try_from(0.1f64).unwrap().to_string(), "0.1000");
+        assert_eq!(Decimal4::try_from(-2.5f64).unwrap().to_string(), "-2.5000");
+        assert!(Decimal4::try_from(f64::NAN).is_err());
+        assert!(Decimal4::try_from(f64::INFINITY).is_err());
+    }
+
     #[test]
     fn test_negative_values() {
         let positive = Decimal4::from_str("1.0").unwrap();
         let negative = Decimal4::from_str("-1.0").unwrap();
 
+        assert!(negative.is_negative());
+        assert!(!positive.is_negative());
+        assert!(!Decimal4::from_str("-0.0").unwrap().is_negative());
+
         assert_eq!((positive - negative).to_string(), "2.0000");
         assert_eq!((negative - positive).to_string(), "-2.0000");
     }
+
+    #[test]
+    fn test_equal_values_are_same_hash_key() {
+        use std::collections::HashMap;
+
+        let mut map = HashMap::new();
+        map.insert(Decimal4::from_str("1.0").unwrap(), "one");
+        assert_eq!(
+            map.get(&Decimal4::from_str("1.0000").unwrap()),
+            Some(&"one")
+        );
+        assert_eq!(map.get(&Decimal4::ONE), Some(&"one"));
+
+        map.insert(Decimal4::ZERO, "zero");
+        assert_eq!(map.get(&Decimal4::from_str("-0.0").unwrap()), Some(&"zero"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_percentage_of() {
+        let d = |s| Decimal4::from_str(s).unwrap();
+        assert_eq!(d("25.0").percentage_of(d("100.0")), Some(d("25.0000")));
+        assert_eq!(d("1").percentage_of(d("3")), Some(d("33.3333")));
+        assert_eq!(d("-5").percentage_of(d("20")), Some(d("-25")));
+        assert_eq!(d("25.0").percentage_of(Decimal4::ZERO), None);
+        assert_eq!(Decimal4::MAX_REPRESENTABLE.percentage_of(d("0.0001")), None);
+    }
+
+    #[test]
+    fn test_minor_units_cents() {
+        let d = Decimal4::from_minor_units(1050, 2).unwrap();
+        assert_eq!(d.to_string(), "10.5000");
+        assert_eq!(d.to_minor_units(2), 1050);
+
+        let d = Decimal4::from_str("10.5099").unwrap();
+        assert_eq!(d.to_minor_units(2), 1050);
+        assert_eq!(d.to_minor_units(4), 105099);
+    }
+
+    #[test]
+    fn test_minor_units_whole() {
+        let d = Decimal4::from_minor_units(42, 0).unwrap();
+        assert_eq!(d.to_string(), "42.0000");
+        assert_eq!(d.to_minor_units(0), 42);
+
+        let d = Decimal4::from_str("42.9999").unwrap();
+        assert_eq!(d.to_minor_units(0), 42);
+    }
+
+    #[test]
+    fn test_minor_units_negative() {
+        let d = Decimal4::from_minor_units(-1050, 2).unwrap();
+        assert_eq!(d.to_string(), "-10.5000");
+        assert_eq!(d.to_minor_units(2), -1050);
+
+        let d = Decimal4::from_str("-1.2399").unwrap();
+        assert_eq!(d.to_minor_units(2), -123);
+        assert_eq!(d.to_minor_units(4), -12399);
+    }
+
+    #[test]
+    fn test_minor_units_invalid_scale() {
+        assert!(Decimal4::from_minor_units(1, 29).is_err());
+    }
+
+    /// Values in `±10^11` with 4 decimal places, small enough that sums and
+    /// products of two values stay representable.
+    fn decimal4() -> impl Strategy<Value = Decimal4> {
+        (-1_000_000_000_000_000i64..=1_000_000_000_000_000)
+            .prop_map(|units| Decimal4::new(Decimal::new(units, Decimal4::SCALE)))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_addition_is_commutative(a in decimal4(), b in decimal4()) {
+            prop_assert_eq!(a + b, b + a);
+        }
+
+        #[test]
+        fn prop_addition_is_associative(a in decimal4(), b in decimal4(), c in decimal4()) {
+            prop_assert_eq!((a + b) + c, a + (b + c));
+        }
+
+        #[test]
+        fn prop_zero_is_additive_identity(a in decimal4()) {
+            prop_assert_eq!(a + Decimal4::ZERO, a);
+            prop_assert_eq!(a - Decimal4::ZERO, a);
+        }
+
+        #[test]
+        fn prop_results_have_scale_4(a in decimal4(), b in decimal4()) {
+            for result in [a + b, a - b, a * b, -a, Decimal4::ZERO + a] {
+                prop_assert_eq!(result.0.scale(), Decimal4::SCALE);
+            }
+            let mut sum = a;
+            sum += b;
+            sum -= b;
+            prop_assert_eq!(sum.0.scale(), Decimal4::SCALE);
+            prop_assert_eq!(sum, a);
+        }
+    }
 }
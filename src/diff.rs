@@ -0,0 +1,60 @@
+//! Comparison of two engine states for reconciliation.
+
+use crate::account::ClientAccount;
+use std::fmt;
+
+/// Differences between two engines' account states.
+///
+/// Produced by [`PaymentsEngine::diff`](crate::PaymentsEngine::diff). All lists
+/// are sorted by client ID.
+#[derive(Debug, Clone, Default)]
+pub struct EngineDiff<'a> {
+    /// Accounts present only in the other engine.
+    pub added: Vec<&'a ClientAccount>,
+
+    /// Accounts present only in this engine.
+    pub removed: Vec<&'a ClientAccount>,
+
+    /// Accounts present in both with different balances or lock state,
+    /// as `(before, after)` pairs.
+    pub changed: Vec<(&'a ClientAccount, &'a ClientAccount)>,
+}
+
+impl EngineDiff<'_> {
+    /// Returns `true` if the two engines have identical account states.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for EngineDiff<'_> {
+    /// Writes one line per difference, prefixed with `+` (added), `-` (removed)
+    /// or a `-`/`+` pair (changed). Accounts are shown in output CSV column order.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for account in &self.removed {
+            writeln!(f, "- {}", AccountRow(account))?;
+        }
+        for account in &self.added {
+            writeln!(f, "+ {}", AccountRow(account))?;
+        }
+        for (before, after) in &self.changed {
+            writeln!(f, "- {}", AccountRow(before))?;
+            writeln!(f, "+ {}", AccountRow(after))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats an account as `client,available,held,total,locked`.
+struct AccountRow<'a>(&'a ClientAccount);
+
+impl fmt::Display for AccountRow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let a = self.0;
+        write!(
+            f,
+            "{},{},{},{},{}",
+            a.client, a.available, a.held, a.total, a.locked
+        )
+    }
+}
@@ -4,14 +4,33 @@
 //! The engine uses streaming CSV processing and stores only deposit transactions
 //! for dispute reference.
 
-use crate::account::ClientAccount;
+use crate::account::{AccountEvent, ClientAccount};
+use crate::builder::PaymentsEngineBuilder;
+use crate::config::EngineConfig;
 use crate::decimal::Decimal4;
-use crate::error::Result;
+use crate::diff::EngineDiff;
+use crate::error::{EngineError, Result, TransactionValidationError};
+use crate::header_map::HeaderMap;
+use crate::hooks::{EngineHooks, NoopHooks};
+use crate::report::{
+    EngineReport, IntegrityReport, IntegrityViolation, ProcessingStats, SimulatedChargebackResult,
+};
+use crate::snapshot::EngineSnapshot;
+use crate::stats::AccountStats;
+#[cfg(feature = "audit_log")]
+use crate::timeline::TransactionEvent;
 use crate::transaction::{ParsedTransaction, StoredTransaction, TransactionRecord, TxKind};
 use csv::{ReaderBuilder, Trim};
+#[cfg(not(feature = "tracing"))]
 use log::{debug, warn};
-use std::collections::HashMap;
-use std::io::{Read, Write};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+#[cfg(feature = "tracing")]
+use tracing::{debug, warn};
 
 /// The payments processing engine.
 ///
@@ -28,455 +47,5583 @@ pub struct PaymentsEngine {
 
     /// Stored deposit transactions for dispute/resolve/chargeback reference.
     transactions: HashMap<u32, StoredTransaction>,
+
+    /// Stored transaction IDs per client, sorted ascending.
+    client_tx_index: HashMap<u16, Vec<u32>>,
+
+    /// IDs of settled deposits removed from `transactions` by
+    /// [`process_csv_chunked`](Self::process_csv_chunked), kept so that
+    /// replaying them is still detected as a duplicate.
+    flushed_tx_ids: HashSet<u32>,
+
+    /// Engine configuration.
+    config: EngineConfig,
+
+    /// Callbacks invoked when transactions change account state.
+    hooks: Box<dyn EngineHooks>,
+
+    /// Counts of applied transactions by type, for reporting.
+    counts: TransactionCounts,
+
+    /// Per-client counts and sums of applied transactions.
+    stats: HashMap<u16, AccountStats>,
+
+    /// Number of transactions applied so far, used to stamp modified accounts.
+    logical_clock: u64,
+}
+
+/// Saved engine state, restored with [`PaymentsEngine::rollback`].
+///
+/// Created by [`PaymentsEngine::checkpoint`]. The contents are opaque.
+#[must_use = "a checkpoint does nothing unless passed to `rollback`"]
+pub struct EngineCheckpoint {
+    state: PaymentsEngine,
+}
+
+/// Running counts of applied transactions.
+#[derive(Debug, Clone, Copy, Default)]
+struct TransactionCounts {
+    deposits: u32,
+    withdrawals: u32,
+    resolves: u32,
+    chargebacks: u32,
+}
+
+impl TransactionCounts {
+    /// Records an applied transaction of the given kind.
+    fn record(&mut self, kind: &TxKind) {
+        let counter = match kind {
+            TxKind::Deposit(_) => &mut self.deposits,
+            TxKind::Withdrawal(_) => &mut self.withdrawals,
+            TxKind::Resolve => &mut self.resolves,
+            TxKind::Chargeback => &mut self.chargebacks,
+            TxKind::Dispute => return,
+        };
+        *counter = counter.saturating_add(1);
+    }
+
+    /// Adds another set of counts to this one.
+    fn merge(&mut self, other: TransactionCounts) {
+        self.deposits = self.deposits.saturating_add(other.deposits);
+        self.withdrawals = self.withdrawals.saturating_add(other.withdrawals);
+        self.resolves = self.resolves.saturating_add(other.resolves);
+        self.chargebacks = self.chargebacks.saturating_add(other.chargebacks);
+    }
 }
 
 impl PaymentsEngine {
     /// Creates a new empty engine.
     pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    /// Creates a new empty engine with the given configuration.
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::with_config_and_hooks(config, Box::new(NoopHooks))
+    }
+
+    /// Creates a new empty engine with room for `clients` accounts and
+    /// `transactions` stored deposits.
+    ///
+    /// Pre-sizing the maps avoids repeated rehashing when the input size is
+    /// roughly known in advance.
+    pub fn with_capacity(clients: usize, transactions: usize) -> Self {
+        let mut engine = Self::new();
+        engine.accounts.reserve(clients);
+        engine.transactions.reserve(transactions);
+        engine.client_tx_index.reserve(clients);
+        engine
+    }
+
+    /// Creates a new empty engine with the given configuration and hooks.
+    pub(crate) fn with_config_and_hooks(config: EngineConfig, hooks: Box<dyn EngineHooks>) -> Self {
         PaymentsEngine {
             accounts: HashMap::new(),
             transactions: HashMap::new(),
+            client_tx_index: HashMap::new(),
+            flushed_tx_ids: HashSet::new(),
+            config,
+            hooks,
+            counts: TransactionCounts::default(),
+            stats: HashMap::new(),
+            logical_clock: 0,
         }
     }
 
-    /// Processes transactions from a CSV reader in streaming fashion.
+    /// Creates a new empty engine that notifies `hooks` of account changes.
+    pub fn with_hooks<H: EngineHooks + 'static>(hooks: H) -> Self {
+        PaymentsEngine {
+            hooks: Box::new(hooks),
+            ..Self::new()
+        }
+    }
+
+    /// Creates an engine from existing account state, e.g. loaded from a database.
     ///
-    /// Records are read one at a time to minimize memory usage.
-    /// Invalid records are logged at warn level and skipped.
-    pub fn process_csv<R: Read>(&mut self, reader: R) -> Result<()> {
-        let mut csv_reader = ReaderBuilder::new()
-            .trim(Trim::All)
-            .flexible(true)
-            .from_reader(reader);
+    /// No transactions are stored; use [`inject_stored_transaction`] to restore
+    /// deposits that may still be disputed. The logical clock resumes from the
+    /// latest `last_modified_at` among the accounts, and any part of an
+    /// account's balance its lifetime totals do not explain is kept as its
+    /// [`opening_balance`](ClientAccount::opening_balance).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::InvalidAccount`] if an account is keyed under a
+    /// different client ID, its `total` does not equal `available + held`, or
+    /// its opening balance overflows.
+    ///
+    /// [`inject_stored_transaction`]: PaymentsEngine::inject_stored_transaction
+    pub fn from_accounts(mut accounts: HashMap<u16, ClientAccount>) -> Result<Self> {
+        for (&client, account) in &mut accounts {
+            if account.client != client {
+                return Err(EngineError::InvalidAccount {
+                    client,
+                    message: format!("keyed under client {client} but has ID {}", account.client),
+                });
+            }
+            if let Some(message) = balance_mismatch(account) {
+                return Err(EngineError::InvalidAccount { client, message });
+            }
+            if !account.capture_opening_balance() {
+                return Err(EngineError::InvalidAccount {
+                    client,
+                    message: "opening balance overflows".to_string(),
+                });
+            }
+        }
 
-        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
-            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+        let mut engine = Self::new();
+        engine.logical_clock = accounts
+            .values()
+            .map(|a| a.last_modified_at)
+            .max()
+            .unwrap_or(0);
+        engine.accounts = accounts;
+        Ok(engine)
+    }
 
-            match result {
-                Ok(record) => {
-                    if let Some(tx) = record.parse() {
-                        if let Err(e) = self.process_transaction(tx, row_num) {
-                            warn!("Row {}: {}", row_num, e);
-                        }
-                    } else {
-                        warn!("Row {}: Failed to parse transaction record", row_num);
-                    }
-                }
-                Err(e) => {
-                    warn!("Row {}: CSV parse error: {}", row_num, e);
-                }
-            }
+    /// Restores a stored deposit so it can later be disputed, resolved or charged back.
+    ///
+    /// If the transaction is marked as under dispute, it is also recorded in
+    /// the owning account's held transaction IDs. Balances are not changed.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::DuplicateTxId`] (with `row` 0) if the transaction ID is already stored
+    /// - [`EngineError::InvalidAccount`] if the owning client has no account
+    pub fn inject_stored_transaction(&mut self, tx: StoredTransaction) -> Result<()> {
+        if self.transactions.contains_key(&tx.tx_id) {
+            return Err(EngineError::DuplicateTxId {
+                tx_id: tx.tx_id,
+                row: 0,
+            });
         }
+        let Some(account) = self.accounts.get_mut(&tx.client) else {
+            return Err(EngineError::InvalidAccount {
+                client: tx.client,
+                message: format!("no account for stored transaction {}", tx.tx_id),
+            });
+        };
 
+        if tx.under_dispute {
+            account.add_held_tx(tx.tx_id);
+        }
+        self.store_transaction(tx);
         Ok(())
     }
 
-    /// Processes a single parsed transaction.
-    fn process_transaction(&mut self, tx: ParsedTransaction, row: usize) -> Result<()> {
-        match tx.kind {
-            TxKind::Deposit(amount) => {
-                self.ensure_account_exists(tx.client);
-
-                // Safety: account was just created/verified above
-                if self
-                    .accounts
-                    .get(&tx.client)
-                    .expect("account exists")
-                    .is_locked()
-                {
-                    debug!(
-                        "Row {}: Ignoring deposit for locked account {}",
-                        row, tx.client
-                    );
-                    return Ok(());
-                }
-                self.process_deposit(tx.tx_id, tx.client, amount, row)?;
+    /// Restores a stored deposit, holding its amount if it is under dispute.
+    ///
+    /// Unlike [`inject_stored_transaction`](Self::inject_stored_transaction),
+    /// which expects balances that already include the hold, this moves the
+    /// amount of a disputed transaction from `available` to `held`. Use it
+    /// when balances were persisted without dispute state. Locked accounts
+    /// are adjusted too. Nothing changes on error.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::InvalidRecord`] (with `row` 0) if the owning client has
+    ///   no account, or if holding a disputed amount larger than `available`
+    ///   would leave it negative while
+    ///   [`allow_negative_available`](EngineConfig::allow_negative_available)
+    ///   is `false`
+    /// - [`EngineError::DuplicateTxId`] (with `row` 0) if the transaction ID is already stored
+    /// - [`EngineError::ArithmeticOverflow`] (with `row` 0) if a balance would overflow
+    pub fn insert_stored_transaction(&mut self, tx: StoredTransaction) -> Result<()> {
+        let Some(account) = self.accounts.get_mut(&tx.client) else {
+            return Err(EngineError::InvalidRecord {
+                row: 0,
+                message: format!(
+                    "no account for client {} of stored transaction {}",
+                    tx.client, tx.tx_id
+                ),
+            });
+        };
+        if self.transactions.contains_key(&tx.tx_id) {
+            return Err(EngineError::DuplicateTxId {
+                tx_id: tx.tx_id,
+                row: 0,
+            });
+        }
+
+        if tx.under_dispute {
+            if !self.config.allow_negative_available && account.available < tx.amount {
+                return Err(EngineError::InvalidRecord {
+                    row: 0,
+                    message: format!(
+                        "holding disputed transaction {} would make available negative",
+                        tx.tx_id
+                    ),
+                });
             }
-            TxKind::Withdrawal(amount) => {
-                self.ensure_account_exists(tx.client);
-
-                // Safety: account was just created/verified above
-                if self
-                    .accounts
-                    .get(&tx.client)
-                    .expect("account exists")
-                    .is_locked()
-                {
-                    debug!(
-                        "Row {}: Ignoring withdrawal for locked account {}",
-                        row, tx.client
-                    );
-                    return Ok(());
+            match (
+                account.available.checked_sub(tx.amount),
+                account.held.checked_add(tx.amount),
+            ) {
+                (Some(available), Some(held)) => {
+                    account.available = available;
+                    account.held = held;
                 }
-                self.process_withdrawal(tx.tx_id, tx.client, amount, row)?;
-            }
-            TxKind::Dispute => {
-                if self.is_account_locked(tx.client) {
-                    debug!(
-                        "Row {}: Ignoring dispute for locked account {}",
-                        row, tx.client
-                    );
-                    return Ok(());
+                _ => {
+                    return Err(EngineError::ArithmeticOverflow {
+                        tx_id: tx.tx_id,
+                        row: 0,
+                    })
                 }
-                self.process_dispute(tx.tx_id, tx.client, row)?;
             }
-            TxKind::Resolve => {
-                if self.is_account_locked(tx.client) {
-                    debug!(
-                        "Row {}: Ignoring resolve for locked account {}",
-                        row, tx.client
-                    );
-                    return Ok(());
-                }
-                self.process_resolve(tx.tx_id, tx.client, row)?;
+        }
+        self.inject_stored_transaction(tx)
+    }
+
+    /// Loads account balances from CSV in the format written by
+    /// [`write_output`](Self::write_output), returning the number of accounts loaded.
+    ///
+    /// Use this to restore state without replaying every transaction. No
+    /// transactions are stored, so deposits made before the export cannot be
+    /// disputed. Either every row is loaded or none are.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::CsvPositionError`] if a row cannot be parsed
+    /// - [`EngineError::InvalidRecord`] if a row's `total` does not equal `available + held`
+    /// - [`EngineError::DuplicateClient`] if a client already has an account
+    ///   or appears more than once
+    pub fn import_csv_accounts<R: Read>(&mut self, reader: R) -> Result<usize> {
+        let mut csv_reader = Self::csv_reader(reader);
+
+        let mut imported: HashMap<u16, ClientAccount> = HashMap::new();
+        for (row_idx, result) in csv_reader.deserialize::<ClientAccount>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            let mut account = result?;
+            if !account.balances_consistent() {
+                return Err(EngineError::InvalidRecord {
+                    row: row_num,
+                    message: format!(
+                        "total {} does not equal available {} + held {}",
+                        account.total, account.available, account.held
+                    ),
+                });
             }
-            TxKind::Chargeback => {
-                if self.is_account_locked(tx.client) {
-                    debug!(
-                        "Row {}: Ignoring chargeback for locked account {}",
-                        row, tx.client
-                    );
-                    return Ok(());
-                }
-                self.process_chargeback(tx.tx_id, tx.client, row)?;
+            let client = account.client;
+            if self.accounts.contains_key(&client) || imported.contains_key(&client) {
+                return Err(EngineError::DuplicateClient { client });
             }
+            account.capture_opening_balance();
+            imported.insert(client, account);
         }
 
-        Ok(())
+        let count = imported.len();
+        self.accounts.extend(imported);
+        Ok(count)
     }
 
-    /// Ensures an account exists for the given client, creating one if needed.
-    fn ensure_account_exists(&mut self, client: u16) {
-        self.accounts
-            .entry(client)
-            .or_insert_with(|| ClientAccount::new(client));
+    /// Captures the current accounts and stored transactions.
+    pub fn snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            accounts: self.accounts.clone(),
+            transactions: self.transactions.clone(),
+            client_tx_index: self.client_tx_index.clone(),
+            offset: 0,
+        }
     }
 
-    /// Checks if an account exists and is locked.
-    fn is_account_locked(&self, client: u16) -> bool {
-        self.accounts
-            .get(&client)
-            .map(|a| a.is_locked())
-            .unwrap_or(false)
+    /// Captures the current state together with an input byte offset, as
+    /// returned by [`process_csv_resumable`](Self::process_csv_resumable).
+    pub fn snapshot_with_offset(&self, offset: u64) -> EngineSnapshot {
+        EngineSnapshot {
+            offset,
+            ..self.snapshot()
+        }
     }
 
-    /// Processes a deposit transaction.
-    fn process_deposit(
-        &mut self,
-        tx_id: u32,
-        client: u16,
-        amount: Decimal4,
-        row: usize,
-    ) -> Result<()> {
-        if self.transactions.contains_key(&tx_id) {
-            warn!("Row {}: Duplicate transaction ID {}, ignoring", row, tx_id);
-            return Ok(());
-        }
+    /// Restores an engine from a snapshot taken with [`snapshot`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`from_accounts`] and [`inject_stored_transaction`], plus
+    /// [`EngineError::InvalidAccount`] for the first client whose entry in
+    /// `client_tx_index` does not list exactly its stored transactions.
+    ///
+    /// [`snapshot`]: PaymentsEngine::snapshot
+    /// [`from_accounts`]: PaymentsEngine::from_accounts
+    /// [`inject_stored_transaction`]: PaymentsEngine::inject_stored_transaction
+    pub fn from_snapshot(snapshot: EngineSnapshot) -> Result<Self> {
+        let mut index = snapshot.client_tx_index;
+        index.retain(|_, ids| !ids.is_empty());
 
-        // Safety: ensure_account_exists was called before this method
-        let account = self.accounts.get_mut(&client).expect("account exists");
+        let mut engine = Self::from_accounts(snapshot.accounts)?;
+        for tx in snapshot.transactions.into_values() {
+            engine.inject_stored_transaction(tx)?;
+        }
 
-        if account.deposit(amount) {
-            self.transactions.insert(
-                tx_id,
-                StoredTransaction::from_deposit(tx_id, client, amount),
-            );
-            debug!("Row {}: Deposited {} to client {}", row, amount, client);
+        let mut clients: Vec<u16> = index
+            .keys()
+            .chain(engine.client_tx_index.keys())
+            .copied()
+            .collect();
+        clients.sort_unstable();
+        if let Some(client) = clients
+            .into_iter()
+            .find(|client| index.get(client) != engine.client_tx_index.get(client))
+        {
+            return Err(EngineError::InvalidAccount {
+                client,
+                message: "client_tx_index does not match the stored transactions".to_string(),
+            });
         }
+        engine.client_tx_index = index;
+        Ok(engine)
+    }
 
-        Ok(())
+    /// Returns a builder for configuring a new engine.
+    pub fn builder() -> PaymentsEngineBuilder {
+        PaymentsEngineBuilder::default()
     }
 
-    /// Processes a withdrawal transaction.
-    fn process_withdrawal(
+    /// Returns the engine's configuration.
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Processes transactions from a CSV reader in streaming fashion.
+    ///
+    /// Records are read one at a time to minimize memory usage.
+    /// Invalid records are logged at warn level and skipped.
+    pub fn process_csv<R: Read>(&mut self, reader: R) -> Result<()> {
+        self.process_with_delimiter(reader, b',')
+    }
+
+    /// Processes transactions from a reader whose fields are separated by
+    /// `delimiter`, e.g. `b'|'` or `b'\t'`.
+    ///
+    /// Processing is otherwise identical to [`process_csv`](Self::process_csv).
+    pub fn process_with_delimiter<R: Read>(&mut self, reader: R, delimiter: u8) -> Result<()> {
+        let mut csv_reader = Self::csv_reader_with_delimiter(reader, delimiter);
+        self.process_csv_records(&mut csv_reader, |_| true, |_| {})
+    }
+
+    /// Processes transactions from a CSV reader, skipping rows whose client
+    /// `client_filter` rejects.
+    ///
+    /// Intended for sharding one input across several engines: skipped rows
+    /// create no accounts and store no transactions. Row numbers in log
+    /// messages still count every input row.
+    pub fn process_csv_with_client_filter<R, F>(
         &mut self,
-        tx_id: u32,
-        client: u16,
-        amount: Decimal4,
-        row: usize,
+        reader: R,
+        client_filter: F,
+    ) -> Result<()>
+    where
+        R: Read,
+        F: Fn(u16) -> bool,
+    {
+        let mut csv_reader = Self::csv_reader(reader);
+        self.process_csv_records(&mut csv_reader, client_filter, |_| {})
+    }
+
+    /// Processes only rows for clients in `min..=max`.
+    ///
+    /// See [`process_csv_with_client_filter`](Self::process_csv_with_client_filter).
+    pub fn process_csv_for_client_range<R: Read>(
+        &mut self,
+        reader: R,
+        min: u16,
+        max: u16,
     ) -> Result<()> {
-        if self.transactions.contains_key(&tx_id) {
-            warn!("Row {}: Duplicate transaction ID {}, ignoring", row, tx_id);
-            return Ok(());
+        self.process_csv_with_client_filter(reader, |client| (min..=max).contains(&client))
+    }
+
+    /// Creates an engine from several CSV files, processed in the given order.
+    ///
+    /// Transaction IDs share one namespace across all files, so the order of
+    /// `paths` determines precedence: if a later file repeats a transaction ID
+    /// already seen in an earlier file, the later row is ignored as a duplicate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be opened or read.
+    pub fn process_csv_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut engine = Self::new();
+        for path in paths {
+            let file = File::open(path)?;
+            engine.process_csv(BufReader::new(file))?;
         }
+        Ok(engine)
+    }
 
-        // Safety: ensure_account_exists was called before this method
-        let account = self.accounts.get_mut(&client).expect("account exists");
+    /// Processes transactions from an in-memory CSV buffer.
+    pub fn process_csv_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.process_csv(data)
+    }
 
-        if account.withdraw(amount) {
-            debug!("Row {}: Withdrew {} from client {}", row, amount, client);
-        } else {
-            debug!(
-                "Row {}: Withdrawal of {} from client {} failed (insufficient funds)",
-                row, amount, client
-            );
-        }
+    /// Processes transactions from a CSV reader, reporting progress.
+    ///
+    /// `progress` is called with the number of data rows read so far every
+    /// [`EngineConfig::progress_interval`] rows. Processing is otherwise
+    /// identical to [`process_csv`](Self::process_csv).
+    pub fn process_csv_with_progress<R, F>(&mut self, reader: R, progress: F) -> Result<()>
+    where
+        R: Read,
+        F: FnMut(usize),
+    {
+        let mut csv_reader = Self::csv_reader(reader);
+        self.process_csv_records(&mut csv_reader, |_| true, progress)
+    }
 
-        Ok(())
+    /// Processes transactions from a CSV reader whose columns use non-standard names.
+    ///
+    /// Header names are translated through `map` before records are parsed;
+    /// processing is otherwise identical to [`process_csv`](Self::process_csv).
+    pub fn process_csv_with_header_map<R: Read>(
+        &mut self,
+        reader: R,
+        map: &HeaderMap,
+    ) -> Result<()> {
+        let mut csv_reader = Self::csv_reader(reader);
+        let headers: csv::StringRecord = csv_reader
+            .headers()?
+            .iter()
+            .map(|column| map.canonical(column))
+            .collect();
+        csv_reader.set_headers(headers);
+        self.process_csv_records(&mut csv_reader, |_| true, |_| {})
     }
 
-    /// Processes a dispute transaction.
+    /// Processes transactions from a CSV reader after checking that the header
+    /// contains the `type`, `client`, `tx` and `amount` columns.
     ///
-    /// A dispute moves funds from available to held. If the client has withdrawn
-    /// funds after the disputed deposit, available may become negative.
-    fn process_dispute(&mut self, tx_id: u32, client: u16, row: usize) -> Result<()> {
-        let stored_tx = match self.transactions.get_mut(&tx_id) {
-            Some(tx) => tx,
-            None => {
-                debug!(
-                    "Row {}: Dispute references unknown transaction {}, ignoring",
-                    row, tx_id
-                );
-                return Ok(());
-            }
-        };
+    /// Column names are compared after trimming and lowercasing, so `" Type"`
+    /// is accepted. Processing is otherwise identical to
+    /// [`process_csv`](Self::process_csv).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::InvalidRecord`] (with `row` 1) naming the missing
+    /// columns before any data row is processed.
+    pub fn process_csv_with_schema_validation<R: Read>(&mut self, reader: R) -> Result<()> {
+        const REQUIRED_COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
 
-        if stored_tx.client != client {
-            warn!(
-                "Row {}: Dispute client {} doesn't match transaction client {}, ignoring",
-                row, client, stored_tx.client
-            );
-            return Ok(());
+        let mut csv_reader = Self::csv_reader(reader);
+        let headers: csv::StringRecord = csv_reader
+            .headers()?
+            .iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+        let missing: Vec<&str> = REQUIRED_COLUMNS
+            .into_iter()
+            .filter(|required| !headers.iter().any(|column| column == *required))
+            .collect();
+        if !missing.is_empty() {
+            return Err(EngineError::InvalidRecord {
+                row: 1,
+                message: format!(
+                    "missing required column(s) {}; found {}",
+                    missing.join(", "),
+                    headers.iter().collect::<Vec<_>>().join(", ")
+                ),
+            });
         }
 
-        if stored_tx.under_dispute {
-            debug!(
-                "Row {}: Transaction {} already under dispute, ignoring",
-                row, tx_id
-            );
-            return Ok(());
-        }
+        csv_reader.set_headers(headers);
+        self.process_csv_records(&mut csv_reader, |_| true, |_| {})
+    }
 
-        let amount = stored_tx.amount;
-        stored_tx.under_dispute = true;
+    /// Processes every record of an already configured CSV reader whose
+    /// client `client_filter` accepts.
+    fn process_csv_records<R, C, F>(
+        &mut self,
+        csv_reader: &mut csv::Reader<R>,
+        client_filter: C,
+        mut progress: F,
+    ) -> Result<()>
+    where
+        R: Read,
+        C: Fn(u16) -> bool,
+        F: FnMut(usize),
+    {
+        let interval = self.config.progress_interval;
 
-        // Safety: disputes reference stored transactions which require an existing account
-        let account = self
-            .accounts
-            .get_mut(&client)
-            .expect("account exists for stored tx");
-        account.hold(amount);
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            if interval > 0 && (row_idx + 1) % interval == 0 {
+                progress(row_idx + 1);
+            }
 
-        debug!(
-            "Row {}: Disputed transaction {} for client {}, holding {}",
-            row, tx_id, client, amount
-        );
+            match result {
+                Ok(record) => {
+                    if client_filter(record.client) {
+                        self.process_record(&record, row_num);
+                    }
+                }
+                Err(e) => {
+                    warn!("Row {}: CSV parse error: {}", row_num, e);
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// Processes a resolve transaction.
-    fn process_resolve(&mut self, tx_id: u32, client: u16, row: usize) -> Result<()> {
-        let stored_tx = match self.transactions.get_mut(&tx_id) {
-            Some(tx) => tx,
-            None => {
-                debug!(
-                    "Row {}: Resolve references unknown transaction {}, ignoring",
-                    row, tx_id
-                );
-                return Ok(());
+    /// Processes a CSV file starting at byte `start_offset`, returning the byte
+    /// offset at which processing stopped.
+    ///
+    /// The header is always read from the start of the input. If
+    /// `start_offset` falls inside a line, that partial line is skipped and
+    /// processing starts at the next complete line. Passing the returned
+    /// offset to a later call (e.g. after more rows were appended) resumes
+    /// where this call left off. Row numbers in logs count from the resume point.
+    pub fn process_csv_resumable<R: Read + Seek>(
+        &mut self,
+        reader: R,
+        start_offset: u64,
+    ) -> Result<u64> {
+        let mut reader = BufReader::new(reader);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut header = Vec::new();
+        reader.read_until(b'\n', &mut header)?;
+        let header_len = header.len() as u64;
+
+        let start = if start_offset > header_len {
+            reader.seek(SeekFrom::Start(start_offset - 1))?;
+            let mut previous = [0u8; 1];
+            let at_line_start = reader.read(&mut previous)? == 1 && previous[0] == b'\n';
+            if !at_line_start {
+                reader.read_until(b'\n', &mut Vec::new())?;
             }
+            reader.stream_position()?
+        } else {
+            header_len
         };
 
-        if stored_tx.client != client {
-            warn!(
-                "Row {}: Resolve client {} doesn't match transaction client {}, ignoring",
-                row, client, stored_tx.client
-            );
-            return Ok(());
-        }
-
-        if !stored_tx.under_dispute {
-            debug!(
-                "Row {}: Transaction {} not under dispute, ignoring resolve",
-                row, tx_id
-            );
-            return Ok(());
-        }
+        let mut csv_reader = Self::csv_reader(header.as_slice().chain(reader));
+        self.process_csv_records(&mut csv_reader, |_| true, |_| {})?;
 
-        let amount = stored_tx.amount;
-        stored_tx.under_dispute = false;
+        let consumed = csv_reader.position().byte().saturating_sub(header_len);
+        Ok(start + consumed)
+    }
 
-        // Safety: resolves reference stored transactions which require an existing account
-        let account = self
-            .accounts
-            .get_mut(&client)
-            .expect("account exists for stored tx");
-        account.release(amount);
+    /// Processes transactions from a CSV reader, giving up once `timeout` has elapsed.
+    ///
+    /// Returns the number of rows successfully applied; malformed rows and
+    /// rows rejected by business rules are not counted. The deadline is
+    /// checked every 1000 rows read, so processing may overrun it by up to
+    /// that many rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::Timeout`] with the number of rows applied so far
+    /// if the deadline passes before the input is exhausted. Rows are applied
+    /// whole, so the engine is left consistent with every row read before the
+    /// deadline handled and none after.
+    pub fn process_csv_with_timeout<R: Read>(
+        &mut self,
+        reader: R,
+        timeout: Duration,
+    ) -> Result<usize> {
+        const CHECK_INTERVAL: usize = 1000;
 
-        debug!(
-            "Row {}: Resolved dispute for transaction {} for client {}, released {}",
-            row, tx_id, client, amount
-        );
+        let start = Instant::now();
+        let mut csv_reader = Self::csv_reader(reader);
+        let mut rows_processed = 0;
+
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            if row_idx > 0 && row_idx % CHECK_INTERVAL == 0 && start.elapsed() > timeout {
+                return Err(EngineError::Timeout { rows_processed });
+            }
+
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            let clock = self.logical_clock;
+            match result {
+                Ok(record) => {
+                    self.process_record(&record, row_num);
+                }
+                Err(e) => {
+                    warn!("Row {}: CSV parse error: {}", row_num, e);
+                }
+            }
+            if self.logical_clock > clock {
+                rows_processed += 1;
+            }
+        }
+
+        Ok(rows_processed)
+    }
+
+    /// Processes at most `limit` data rows from a CSV reader, returning the
+    /// number of rows processed.
+    ///
+    /// The header row is not counted. Malformed rows count towards the limit,
+    /// as they are logged and skipped like in [`process_csv`](Self::process_csv).
+    /// The reader is buffered, so it may be consumed past the last processed
+    /// row; use [`process_csv_resumable`](Self::process_csv_resumable) to
+    /// continue from an exact byte offset.
+    pub fn process_csv_with_row_limit<R: Read>(
+        &mut self,
+        reader: R,
+        limit: usize,
+    ) -> Result<usize> {
+        let mut csv_reader = Self::csv_reader(reader);
+        let mut rows_processed = 0;
+
+        for (row_idx, result) in csv_reader
+            .deserialize::<TransactionRecord>()
+            .take(limit)
+            .enumerate()
+        {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            match result {
+                Ok(record) => {
+                    self.process_record(&record, row_num);
+                }
+                Err(e) => {
+                    warn!("Row {}: CSV parse error: {}", row_num, e);
+                }
+            }
+            rows_processed += 1;
+        }
+
+        Ok(rows_processed)
+    }
+
+    /// Processes transactions from a CSV reader, removing settled deposits from
+    /// memory after every `chunk_size` rows.
+    ///
+    /// A stored deposit is settled when it has never been disputed. Flushed
+    /// deposits can no longer be disputed, so this trades dispute coverage for
+    /// bounded memory; only their transaction IDs are kept, so that replaying
+    /// one is still ignored as a duplicate. A `chunk_size` of zero disables
+    /// flushing. Rows are otherwise handled as in [`process_csv`](Self::process_csv).
+    pub fn process_csv_chunked<R: Read>(
+        &mut self,
+        reader: R,
+        chunk_size: usize,
+    ) -> Result<ProcessingStats> {
+        let mut csv_reader = Self::csv_reader(reader);
+        let mut stats = ProcessingStats::default();
+
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            let parsed = match result {
+                Ok(record) => self.process_record(&record, row_num),
+                Err(e) => {
+                    warn!("Row {}: CSV parse error: {}", row_num, e);
+                    false
+                }
+            };
+            stats.rows_read += 1;
+            if !parsed {
+                stats.rows_skipped += 1;
+            }
+            if chunk_size > 0 && stats.rows_read % chunk_size == 0 {
+                stats.transactions_flushed += self.flush_settled_transactions();
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Removes stored deposits that were never disputed, returning how many
+    /// were removed.
+    fn flush_settled_transactions(&mut self) -> usize {
+        let before = self.transactions.len();
+        let flushed = &mut self.flushed_tx_ids;
+        self.transactions.retain(|&tx_id, tx| {
+            let keep = tx.under_dispute || tx.dispute_count > 0;
+            if !keep {
+                flushed.insert(tx_id);
+            }
+            keep
+        });
+        let transactions = &self.transactions;
+        self.client_tx_index.retain(|_, ids| {
+            ids.retain(|id| transactions.contains_key(id));
+            !ids.is_empty()
+        });
+        before - self.transactions.len()
+    }
+
+    /// Processes raw transaction records already held in memory.
+    ///
+    /// Rows are numbered from 1 in vector order. Invalid records are logged at
+    /// warn level and skipped, as in [`process_csv`](Self::process_csv).
+    pub fn process_batch(&mut self, records: Vec<TransactionRecord>) -> Result<()> {
+        for (idx, record) in records.iter().enumerate() {
+            self.process_record(record, idx + 1);
+        }
 
         Ok(())
     }
 
-    /// Processes a chargeback transaction.
-    fn process_chargeback(&mut self, tx_id: u32, client: u16, row: usize) -> Result<()> {
-        let stored_tx = match self.transactions.get_mut(&tx_id) {
-            Some(tx) => tx,
-            None => {
-                debug!(
-                    "Row {}: Chargeback references unknown transaction {}, ignoring",
-                    row, tx_id
-                );
-                return Ok(());
+    /// Parses and processes a single raw record, logging any failure.
+    ///
+    /// Returns `false` if the record could not be parsed.
+    fn process_record(&mut self, record: &TransactionRecord, row_num: usize) -> bool {
+        match self.parse_record(record, row_num) {
+            Ok(tx) => {
+                self.process_transaction_logged(tx, row_num);
+                true
             }
-        };
+            Err(e) => {
+                warn!("{}", e);
+                false
+            }
+        }
+    }
 
-        if stored_tx.client != client {
+    /// Processes a parsed transaction, logging any failure instead of returning it.
+    fn process_transaction_logged(&mut self, tx: ParsedTransaction, row_num: usize) {
+        if let Err(e) = self.process_transaction(tx, row_num) {
+            warn!("Row {}: {}", row_num, e);
+        }
+    }
+
+    /// Processes transactions from a CSV reader, rejecting the whole input if
+    /// any row is invalid.
+    ///
+    /// All rows are parsed before any is applied, so a malformed row leaves the
+    /// engine unchanged. If applying a transaction fails (e.g. arithmetic
+    /// overflow), account and transaction state is rolled back to where it was
+    /// before the call; hooks already notified for earlier rows are not undone.
+    ///
+    /// Rows that are well-formed but rejected by business rules (insufficient
+    /// funds, unknown dispute references, locked accounts) are not errors
+    /// unless [`EngineConfig::strict_business_rules`] is set, in which case
+    /// insufficient funds, locked accounts and references to another client's
+    /// transaction fail the call.
+    pub fn process_csv_strict<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut csv_reader = Self::csv_reader(reader);
+
+        let mut parsed = Vec::new();
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            let tx = self.parse_record_checked(&result?, row_num)?;
+            parsed.push((row_num, tx));
+        }
+
+        let accounts_snapshot = self.accounts.clone();
+        let transactions_snapshot = self.transactions.clone();
+        let client_tx_index_snapshot = self.client_tx_index.clone();
+        let counts_snapshot = self.counts;
+        let stats_snapshot = self.stats.clone();
+        let logical_clock_snapshot = self.logical_clock;
+        for (row_num, tx) in parsed {
+            if let Err(e) = self.process_transaction(tx, row_num) {
+                self.accounts = accounts_snapshot;
+                self.transactions = transactions_snapshot;
+                self.client_tx_index = client_tx_index_snapshot;
+                self.counts = counts_snapshot;
+                self.stats = stats_snapshot;
+                self.logical_clock = logical_clock_snapshot;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates every row of a CSV against a copy of the engine, returning
+    /// all row-level errors without modifying `self`.
+    ///
+    /// Unlike [`process_csv_strict`](Self::process_csv_strict), processing
+    /// continues after an error so every problem is reported. Errors are the
+    /// ones strict processing would return: malformed rows, negative amounts,
+    /// arithmetic overflow, and, depending on the configuration, duplicate
+    /// IDs and business rule violations. Hooks are not called.
+    pub fn dry_run<R: Read>(&self, reader: R) -> Vec<(usize, EngineError)> {
+        let mut scratch = self.clone();
+        let mut errors = Vec::new();
+        let mut csv_reader = Self::csv_reader(reader);
+
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            let outcome = result
+                .map_err(EngineError::from)
+                .and_then(|record| scratch.parse_record_checked(&record, row_num))
+                .and_then(|tx| scratch.process_transaction(tx, row_num));
+            if let Err(e) = outcome {
+                errors.push((row_num, e));
+            }
+        }
+
+        errors
+    }
+
+    /// Parses a record for lenient processing.
+    ///
+    /// Amounts on disputes, resolves and chargebacks are ignored unless
+    /// [`EngineConfig::strict_business_rules`] is set, in which case the
+    /// record is checked like in strict processing.
+    fn parse_record(
+        &self,
+        record: &TransactionRecord,
+        row_num: usize,
+    ) -> Result<ParsedTransaction> {
+        if self.config.strict_business_rules {
+            return self.parse_record_checked(record, row_num);
+        }
+        record.try_parse_with_rounding(row_num, self.config.rounding_mode)
+    }
+
+    /// Parses a record, turning validation failures into errors.
+    fn parse_record_checked(
+        &self,
+        record: &TransactionRecord,
+        row_num: usize,
+    ) -> Result<ParsedTransaction> {
+        record.validate().map_err(|e| {
+            // Amounts that only become zero through rounding keep the raw
+            // input in the message rather than reporting a negative zero.
+            let negative = record
+                .raw_amount(self.config.rounding_mode)
+                .filter(|amount| amount.is_negative());
+            match (e, negative) {
+                (TransactionValidationError::NegativeAmount(_), Some(amount)) => {
+                    EngineError::NegativeAmount {
+                        tx_id: record.tx,
+                        amount,
+                    }
+                }
+                (e, _) => EngineError::InvalidRecord {
+                    row: row_num,
+                    message: e.to_string(),
+                },
+            }
+        })?;
+        record.try_parse_with_rounding(row_num, self.config.rounding_mode)
+    }
+
+    /// Builds a CSV reader with the engine's input settings.
+    fn csv_reader<R: Read>(reader: R) -> csv::Reader<R> {
+        Self::csv_reader_with_delimiter(reader, b',')
+    }
+
+    /// Builds a reader with the engine's input settings and the given field delimiter.
+    fn csv_reader_with_delimiter<R: Read>(reader: R, delimiter: u8) -> csv::Reader<R> {
+        ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(Trim::All)
+            .flexible(true)
+            .from_reader(reader)
+    }
+
+    /// Processes transactions from an async reader.
+    ///
+    /// The input is buffered in full before processing so that no engine
+    /// state is modified across `.await` points; parsing then follows the
+    /// same rules as [`process_csv`](Self::process_csv).
+    #[cfg(feature = "tokio")]
+    pub async fn process_csv_async<R>(&mut self, mut reader: R) -> Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await?;
+        self.process_csv(buffer.as_slice())
+    }
+
+    /// Processes transactions from a CSV reader, applying each client's
+    /// transactions in parallel.
+    ///
+    /// All rows are read and parsed first, then grouped by client. Each group
+    /// is applied in input order on its own engine using the rayon thread
+    /// pool, and the results are merged back. Clients whose transaction IDs
+    /// collide with another client's (in the input or already stored) depend
+    /// on each other through duplicate detection, so their rows are applied
+    /// afterwards in a sequential post-pass, in input order. The final state
+    /// matches [`process_csv`](Self::process_csv), except that
+    /// `last_modified_at` stamps are only ordered within each client.
+    ///
+    /// With `max_clients` or `max_transactions` configured, limits depend on
+    /// the order across clients, and hooks other than [`NoopHooks`] must be
+    /// notified in input order, so in either case all rows are applied
+    /// sequentially.
+    #[cfg(feature = "rayon")]
+    pub fn process_csv_concurrent<R: Read>(&mut self, reader: R) -> Result<()> {
+        use rayon::prelude::*;
+        use std::collections::hash_map::Entry;
+        use std::collections::HashSet;
+
+        let mut rows = Vec::new();
+        let mut csv_reader = Self::csv_reader(reader);
+        for (row_idx, result) in csv_reader.deserialize::<TransactionRecord>().enumerate() {
+            let row_num = row_idx + 2; // 1-indexed, accounting for header row
+            match result {
+                Ok(record) => match self.parse_record(&record, row_num) {
+                    Ok(tx) => rows.push((row_num, tx)),
+                    Err(e) => warn!("{}", e),
+                },
+                Err(e) => warn!("Row {}: CSV parse error: {}", row_num, e),
+            }
+        }
+
+        if self.config.max_clients.is_some()
+            || self.config.max_transactions.is_some()
+            || !self.hooks.is_noop()
+        {
+            for (row_num, tx) in rows {
+                self.process_transaction_logged(tx, row_num);
+            }
+            return Ok(());
+        }
+
+        let mut tx_owners: HashMap<u32, u16> = self
+            .transactions
+            .values()
+            .map(|tx| (tx.tx_id, tx.client))
+            .collect();
+        let mut entangled = HashSet::new();
+        for (_, tx) in &rows {
+            match tx_owners.entry(tx.tx_id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(tx.client);
+                }
+                Entry::Occupied(entry) if *entry.get() != tx.client => {
+                    entangled.insert(*entry.get());
+                    entangled.insert(tx.client);
+                }
+                Entry::Occupied(_) => {}
+            }
+        }
+
+        let mut by_client: HashMap<u16, Vec<(usize, ParsedTransaction)>> = HashMap::new();
+        let mut post_pass = Vec::new();
+        for (row_num, tx) in rows {
+            if entangled.contains(&tx.client) {
+                post_pass.push((row_num, tx));
+            } else {
+                by_client.entry(tx.client).or_default().push((row_num, tx));
+            }
+        }
+
+        let start_clock = self.logical_clock;
+        let shards: Vec<_> = by_client
+            .into_iter()
+            .map(|(client, txs)| {
+                let mut shard = PaymentsEngine::with_config(self.config.clone());
+                shard.logical_clock = start_clock;
+                self.move_client_to(client, &mut shard);
+                (shard, txs)
+            })
+            .collect();
+        let shards: Vec<PaymentsEngine> = shards
+            .into_par_iter()
+            .map(|(mut shard, txs)| {
+                for (row_num, tx) in txs {
+                    shard.process_transaction_logged(tx, row_num);
+                }
+                shard
+            })
+            .collect();
+
+        let applied: u64 = shards.iter().map(|s| s.logical_clock - start_clock).sum();
+        for shard in shards {
+            self.merge_from(shard)?;
+        }
+        self.logical_clock = start_clock + applied;
+
+        for (row_num, tx) in post_pass {
+            self.process_transaction_logged(tx, row_num);
+        }
+        Ok(())
+    }
+
+    /// Deposits `amount` to each client in `client_ids`, creating accounts as needed.
+    ///
+    /// Deposits use transaction IDs `base_tx_id`, `base_tx_id + 1`, … in list
+    /// order. If any of those IDs is already stored (or the range would
+    /// overflow `u32`), nothing is applied and 0 is returned. Otherwise each
+    /// deposit goes through the normal rules, so e.g. locked accounts are
+    /// skipped. Returns the number of deposits applied.
+    pub fn bulk_deposit(&mut self, client_ids: &[u16], amount: Decimal4, base_tx_id: u32) -> usize {
+        let Some(tx_ids) = u32::try_from(client_ids.len())
+            .ok()
+            .and_then(|len| base_tx_id.checked_add(len))
+            .map(|end| base_tx_id..end)
+        else {
+            return 0;
+        };
+        if tx_ids.clone().any(|id| self.is_known_tx(id)) {
             warn!(
-                "Row {}: Chargeback client {} doesn't match transaction client {}, ignoring",
-                row, client, stored_tx.client
+                "Bulk deposit transaction IDs starting at {} overlap stored transactions, ignoring",
+                base_tx_id
             );
-            return Ok(());
+            return 0;
         }
 
-        if !stored_tx.under_dispute {
-            debug!(
-                "Row {}: Transaction {} not under dispute, ignoring chargeback",
-                row, tx_id
+        let mut applied = 0;
+        for (idx, (&client, tx_id)) in client_ids.iter().zip(tx_ids).enumerate() {
+            self.process_transaction_logged(
+                ParsedTransaction {
+                    tx_id,
+                    client,
+                    kind: TxKind::Deposit(amount),
+                },
+                idx + 1,
             );
-            return Ok(());
+            if self.transactions.contains_key(&tx_id) {
+                applied += 1;
+            }
+        }
+        applied
+    }
+
+    /// Processes already-parsed transactions from an iterator.
+    ///
+    /// Bypasses the CSV layer for callers that build `ParsedTransaction` values
+    /// programmatically. Rows are numbered from 1 in iteration order.
+    pub fn process_transactions_iter<I>(&mut self, iter: I) -> Result<()>
+    where
+        I: Iterator<Item = ParsedTransaction>,
+    {
+        for (idx, tx) in iter.enumerate() {
+            self.process_transaction_logged(tx, idx + 1);
         }
 
-        let amount = stored_tx.amount;
-        stored_tx.under_dispute = false;
+        Ok(())
+    }
+
+    /// Creates an engine and processes transactions received from a channel
+    /// until all senders are dropped.
+    ///
+    /// This is the async counterpart of
+    /// [`process_transactions_iter`](Self::process_transactions_iter), with
+    /// rows numbered from 1 in receive order.
+    #[cfg(feature = "tokio")]
+    pub async fn process_channel(
+        mut rx: tokio::sync::mpsc::Receiver<ParsedTransaction>,
+    ) -> Result<Self> {
+        let mut engine = Self::new();
+        let mut row_num = 0;
+        while let Some(tx) = rx.recv().await {
+            row_num += 1;
+            engine.process_transaction_logged(tx, row_num);
+        }
+
+        Ok(engine)
+    }
+
+    /// Merges all accounts and stored transactions from another engine.
+    ///
+    /// Intended for sharded inputs where each engine processed a disjoint set
+    /// of clients, possibly concurrently. Conflicts are checked before any state
+    /// is modified, so on error `self` is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::DuplicateClient`] if both engines hold an account for the same client
+    /// - [`EngineError::DuplicateTxId`] (with `row` 0) if both engines stored the same transaction ID
+    pub fn merge_from(&mut self, other: PaymentsEngine) -> Result<()> {
+        if let Some(&client) = other
+            .accounts
+            .keys()
+            .find(|c| self.accounts.contains_key(c))
+        {
+            return Err(EngineError::DuplicateClient { client });
+        }
+        if let Some(&tx_id) = other
+            .transactions
+            .keys()
+            .find(|&&t| self.is_known_tx(t))
+            .or_else(|| {
+                other
+                    .flushed_tx_ids
+                    .iter()
+                    .find(|t| self.transactions.contains_key(t))
+            })
+        {
+            return Err(EngineError::DuplicateTxId { tx_id, row: 0 });
+        }
+
+        self.accounts.extend(other.accounts);
+        self.transactions.extend(other.transactions);
+        self.flushed_tx_ids.extend(other.flushed_tx_ids);
+        for (client, tx_ids) in other.client_tx_index {
+            let ids = self.client_tx_index.entry(client).or_default();
+            ids.extend(tx_ids);
+            ids.sort_unstable();
+        }
+        self.counts.merge(other.counts);
+        self.stats.extend(other.stats);
+        self.logical_clock = self.logical_clock.max(other.logical_clock);
+        Ok(())
+    }
+
+    /// Replays the stored transactions through a fresh engine with the same
+    /// configuration.
+    ///
+    /// Stored deposits are applied in ascending transaction ID order, then
+    /// those currently under dispute are disputed again. This is a best-effort
+    /// reconstruction: withdrawals, resolved disputes and chargebacks are not
+    /// stored, so the result only equals `self` for inputs made of deposits
+    /// and open disputes. Dispute counts restart from the replayed disputes
+    /// and hooks are not carried over.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::ArithmeticOverflow`] if replaying a transaction
+    /// would overflow a balance. Row numbers count replayed transactions from 1.
+    pub fn reprocess(&self) -> Result<PaymentsEngine> {
+        let mut stored: Vec<&StoredTransaction> = self.transactions.values().collect();
+        stored.sort_by_key(|tx| tx.tx_id);
+
+        let deposits = stored.iter().map(|tx| ParsedTransaction {
+            tx_id: tx.tx_id,
+            client: tx.client,
+            kind: TxKind::Deposit(tx.amount),
+        });
+        let disputes = stored
+            .iter()
+            .filter(|tx| tx.under_dispute)
+            .map(|tx| ParsedTransaction {
+                tx_id: tx.tx_id,
+                client: tx.client,
+                kind: TxKind::Dispute,
+            });
+
+        let mut engine = PaymentsEngine::with_config(self.config.clone());
+        for (idx, tx) in deposits.chain(disputes).enumerate() {
+            engine.process_transaction(tx, idx + 1)?;
+        }
+        Ok(engine)
+    }
+
+    /// Reverses the chargeback of stored deposit `tx_id` on `client`'s account,
+    /// e.g. after a chargeback was submitted for the wrong client.
+    ///
+    /// **Administrative use only.** This bypasses the normal rules: the
+    /// account is unlocked, the deposit's funds are held again and the
+    /// transaction is put back under dispute, so it can then be resolved or
+    /// charged back as usual. The revert is recorded in the audit log with
+    /// row 0 and the logical clock advances, but transaction counters and
+    /// statistics are not changed. Nothing changes on error.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::InvalidAccount`] if the client has no account, the
+    ///   transaction is not a stored deposit of this client, it is still under
+    ///   dispute, or it has not been charged back
+    /// - [`EngineError::ArithmeticOverflow`] (with `row` 0) if a balance would overflow
+    pub fn admin_revert_chargeback(&mut self, client: u16, tx_id: u32) -> Result<()> {
+        let invalid = |message: String| EngineError::InvalidAccount { client, message };
 
-        // Safety: chargebacks reference stored transactions which require an existing account
         let account = self
             .accounts
             .get_mut(&client)
-            .expect("account exists for stored tx");
-        account.chargeback(amount);
+            .ok_or_else(|| invalid("no such account".to_string()))?;
+        let stored_tx = self
+            .transactions
+            .get_mut(&tx_id)
+            .filter(|tx| tx.client == client)
+            .ok_or_else(|| invalid(format!("no stored deposit {tx_id} for this client")))?;
+        if stored_tx.under_dispute {
+            return Err(invalid(format!(
+                "transaction {tx_id} is still under dispute"
+            )));
+        }
+        if !stored_tx.charged_back || account.total_charged_back() < stored_tx.amount {
+            return Err(invalid(format!(
+                "no chargeback of {} to revert for transaction {tx_id}",
+                stored_tx.amount
+            )));
+        }
+        if !account.revert_chargeback(stored_tx.amount) {
+            return Err(EngineError::ArithmeticOverflow { tx_id, row: 0 });
+        }
 
-        debug!(
-            "Row {}: Chargeback for transaction {} for client {}, removed {}, account locked",
-            row, tx_id, client, amount
+        account.add_held_tx(tx_id);
+        account.record_event(
+            0,
+            AccountEvent::ChargebackReverted {
+                tx_id,
+                amount: stored_tx.amount,
+            },
         );
-
+        stored_tx.under_dispute = true;
+        stored_tx.charged_back = false;
+        self.logical_clock += 1;
+        account.last_modified_at = self.logical_clock;
         Ok(())
     }
 
-    /// Writes final account states to CSV.
+    /// Merges the `source` client's account into `target`, e.g. to combine
+    /// duplicate accounts of one user.
     ///
-    /// Output is sorted by client ID in ascending order for deterministic results.
-    /// All monetary values are formatted with exactly 4 decimal places.
-    pub fn write_output<W: Write>(&self, writer: W) -> Result<()> {
-        let mut csv_writer = csv::Writer::from_writer(writer);
-
-        csv_writer.write_record(["client", "available", "held", "total", "locked"])?;
-
-        // Sort by client ID for deterministic output
-        let mut accounts: Vec<_> = self.accounts.values().collect();
-        accounts.sort_by_key(|a| a.client);
+    /// Balances and lifetime totals are added to `target`, the `source` audit
+    /// log is appended to the target's, stored transactions (including those
+    /// under dispute) are re-pointed to `target`, and the `source` account is
+    /// removed. The merge advances the logical clock and stamps `target` as
+    /// modified. Nothing changes on error.
+    ///
+    /// # Errors
+    ///
+    /// - [`EngineError::AccountLocked`] if either account is locked
+    /// - [`EngineError::InvalidAccount`] if either client has no account,
+    ///   `source == target`, or the merged balances would overflow or break
+    ///   `total == available + held`
+    pub fn merge_accounts(&mut self, source: u16, target: u16) -> Result<()> {
+        if source == target {
+            return Err(EngineError::InvalidAccount {
+                client: source,
+                message: "cannot merge an account into itself".to_string(),
+            });
+        }
+        for client in [source, target] {
+            match self.accounts.get(&client) {
+                None => {
+                    return Err(EngineError::InvalidAccount {
+                        client,
+                        message: "no account to merge".to_string(),
+                    })
+                }
+                Some(account) if account.locked => {
+                    return Err(EngineError::AccountLocked { client })
+                }
+                Some(_) => {}
+            }
+        }
 
-        for account in accounts {
-            csv_writer.write_record([
-                account.client.to_string(),
-                account.available.to_string(),
-                account.held.to_string(),
-                account.total.to_string(),
-                account.locked.to_string(),
-            ])?;
+        let mut merged = self.accounts[&target].clone();
+        if !merged.absorb(&self.accounts[&source]) {
+            return Err(EngineError::InvalidAccount {
+                client: target,
+                message: format!("merging client {source} would overflow a balance"),
+            });
+        }
+        if let Some(message) = balance_mismatch(&merged) {
+            return Err(EngineError::InvalidAccount {
+                client: target,
+                message: format!("{message} after merge"),
+            });
         }
 
-        csv_writer.flush()?;
+        self.logical_clock += 1;
+        merged.last_modified_at = self.logical_clock;
+        self.accounts.remove(&source);
+        self.accounts.insert(target, merged);
+        if let Some(tx_ids) = self.client_tx_index.remove(&source) {
+            for tx_id in &tx_ids {
+                if let Some(tx) = self.transactions.get_mut(tx_id) {
+                    tx.client = target;
+                }
+            }
+            let ids = self.client_tx_index.entry(target).or_default();
+            ids.extend(tx_ids);
+            ids.sort_unstable();
+        }
+        if let Some(source_stats) = self.stats.remove(&source) {
+            self.stats
+                .entry(target)
+                .or_insert_with(|| AccountStats::new(target))
+                .absorb(&source_stats);
+        }
         Ok(())
     }
 
-    /// Returns a reference to an account (for testing).
-    #[cfg(test)]
-    pub fn get_account(&self, client_id: u16) -> Option<&ClientAccount> {
-        self.accounts.get(&client_id)
+    /// Saves the current state so a block of transactions can be applied
+    /// tentatively and undone with [`rollback`](Self::rollback).
+    ///
+    /// The checkpoint holds a full copy of accounts and stored transactions.
+    pub fn checkpoint(&self) -> EngineCheckpoint {
+        EngineCheckpoint {
+            state: self.clone(),
+        }
     }
-}
 
-impl Default for PaymentsEngine {
-    fn default() -> Self {
-        Self::new()
+    /// Restores the state saved by [`checkpoint`](Self::checkpoint).
+    ///
+    /// Everything applied since the checkpoint is discarded, including
+    /// counters and the logical clock. Hooks are kept, but calls they
+    /// already received are not undone.
+    pub fn rollback(&mut self, checkpoint: EngineCheckpoint) {
+        let hooks = std::mem::replace(&mut self.hooks, Box::new(NoopHooks));
+        *self = checkpoint.state;
+        self.hooks = hooks;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+    /// Processes a single parsed transaction.
+    ///
+    /// Hooks are notified only for transactions that change account state.
+    fn process_transaction(&mut self, mut tx: ParsedTransaction, row: usize) -> Result<()> {
+        let mut shortfall = Decimal4::ZERO;
+        let applied = match tx.kind {
+            TxKind::Deposit(amount) => {
+                if !self.ensure_account_exists(tx.client, row) {
+                    return Ok(());
+                }
+
+                if self.is_account_locked(tx.client) {
+                    return self.reject_locked(&tx, row);
+                }
+                self.process_deposit(tx.tx_id, tx.client, amount, row)?
+            }
+            TxKind::Withdrawal(amount) => {
+                if !self.ensure_account_exists(tx.client, row) {
+                    return Ok(());
+                }
+
+                if self.is_account_locked(tx.client) {
+                    return self.reject_locked(&tx, row);
+                }
+                match self.process_withdrawal(tx.tx_id, tx.client, amount, row)? {
+                    Some((actual, missing)) => {
+                        // Record partial withdrawals with the amount actually withdrawn.
+                        tx.kind = TxKind::Withdrawal(actual);
+                        shortfall = missing;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            TxKind::Dispute => {
+                if self.is_account_locked(tx.client) {
+                    return self.reject_locked(&tx, row);
+                }
+                self.process_dispute(tx.tx_id, tx.client, row)?
+            }
+            TxKind::Resolve => {
+                if self.is_account_locked(tx.client) {
+                    return self.reject_locked(&tx, row);
+                }
+                self.process_resolve(tx.tx_id, tx.client, row)?
+            }
+            TxKind::Chargeback => {
+                if self.is_account_locked(tx.client) {
+                    return self.reject_locked(&tx, row);
+                }
+                self.process_chargeback(tx.tx_id, tx.client, row)?
+            }
+        };
+
+        if applied {
+            self.record_applied(&tx);
+            if !shortfall.is_zero() {
+                let account = &self.accounts[&tx.client];
+                self.hooks.on_partial_withdrawal(&tx, account, shortfall);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates the clock, counters and statistics for an applied transaction
+    /// and notifies the hooks.
+    fn record_applied(&mut self, tx: &ParsedTransaction) {
+        self.logical_clock += 1;
+        if let Some(account) = self.accounts.get_mut(&tx.client) {
+            account.last_modified_at = self.logical_clock;
+        }
+        self.counts.record(&tx.kind);
+        self.stats
+            .entry(tx.client)
+            .or_insert_with(|| AccountStats::new(tx.client))
+            .record(&tx.kind);
+        self.notify_hooks(tx);
+    }
+
+    /// Handles a transaction for a locked account: an error with
+    /// `strict_business_rules`, otherwise logged and skipped.
+    fn reject_locked(&self, tx: &ParsedTransaction, row: usize) -> Result<()> {
+        if self.config.strict_business_rules {
+            return Err(EngineError::AccountLocked { client: tx.client });
+        }
+        debug!(
+            "Row {}: Ignoring {} for locked account {}",
+            row,
+            tx.kind.name(),
+            tx.client
+        );
+        Ok(())
+    }
+
+    /// Handles a dispute, resolve or chargeback naming a different client than
+    /// the referenced transaction: an error with `strict_business_rules`,
+    /// otherwise logged and skipped.
+    fn reject_client_mismatch(
+        &self,
+        tx_id: u32,
+        claimed_client: u16,
+        actual_client: u16,
+        operation: &'static str,
+        row: usize,
+    ) -> Result<bool> {
+        if self.config.strict_business_rules {
+            return Err(EngineError::TransactionClientMismatch {
+                tx_id,
+                claimed_client,
+                actual_client,
+                operation,
+            });
+        }
+        warn!(
+            "Row {}: {} client {} doesn't match transaction client {}, ignoring",
+            row, operation, claimed_client, actual_client
+        );
+        Ok(false)
+    }
+
+    /// Notifies the registered hooks of an applied transaction.
+    fn notify_hooks(&mut self, tx: &ParsedTransaction) {
+        // Safety: applied transactions always have an account
+        let account = self.accounts.get(&tx.client).expect("account exists");
+
+        match tx.kind {
+            TxKind::Deposit(_) => self.hooks.on_deposit(tx, account),
+            TxKind::Withdrawal(_) => self.hooks.on_withdrawal(tx, account),
+            TxKind::Dispute => self.hooks.on_dispute(tx, account),
+            TxKind::Resolve => self.hooks.on_resolve(tx, account),
+            TxKind::Chargeback => {
+                self.hooks.on_chargeback(tx, account);
+                self.hooks.on_account_locked(tx, account);
+            }
+        }
+    }
+
+    /// Ensures an account exists for the given client, creating one if needed.
+    ///
+    /// Returns `false` if the account does not exist and `max_clients` is reached.
+    fn ensure_account_exists(&mut self, client: u16, row: usize) -> bool {
+        if !self.accounts.contains_key(&client) {
+            if let Some(max) = self.config.max_clients {
+                if self.accounts.len() >= max {
+                    warn!(
+                        "Row {}: Client limit of {} reached, ignoring transaction for client {}",
+                        row, max, client
+                    );
+                    return false;
+                }
+            }
+        }
+        self.accounts
+            .entry(client)
+            .or_insert_with(|| ClientAccount::new(client));
+        true
+    }
+
+    /// Returns `true` if `tx_id` is already stored and the row should be skipped.
+    ///
+    /// With `strict_duplicate_tx_ids` set, a duplicate is an error instead.
+    fn is_duplicate_tx(&self, tx_id: u32, row: usize) -> Result<bool> {
+        if !self.is_known_tx(tx_id) {
+            return Ok(false);
+        }
+        if self.config.strict_duplicate_tx_ids {
+            return Err(EngineError::DuplicateTxId { tx_id, row });
+        }
+        warn!("Row {}: Duplicate transaction ID {}, ignoring", row, tx_id);
+        Ok(true)
+    }
+
+    /// Returns `true` if `tx_id` is stored or was flushed as settled.
+    fn is_known_tx(&self, tx_id: u32) -> bool {
+        self.transactions.contains_key(&tx_id) || self.flushed_tx_ids.contains(&tx_id)
+    }
+
+    /// Checks if an account exists and is locked.
+    fn is_account_locked(&self, client: u16) -> bool {
+        self.accounts
+            .get(&client)
+            .map(|a| a.is_locked())
+            .unwrap_or(false)
+    }
+
+    /// Processes a deposit transaction.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, client),
+            fields(client_id = client, tx_type = "deposit")
+        )
+    )]
+    fn process_deposit(
+        &mut self,
+        tx_id: u32,
+        client: u16,
+        amount: Decimal4,
+        row: usize,
+    ) -> Result<bool> {
+        if self.is_duplicate_tx(tx_id, row)? {
+            return Ok(false);
+        }
+        if amount.is_negative() {
+            warn!("Row {}: Negative deposit amount {}, ignoring", row, amount);
+            return Ok(false);
+        }
+        if amount.is_zero() && self.config.reject_zero_amounts {
+            warn!("Row {}: Zero deposit amount, ignoring", row);
+            return Ok(false);
+        }
+        if let Some(max) = self.config.max_transactions {
+            if self.transactions.len() >= max {
+                warn!(
+                    "Row {}: Limit of {} stored transactions reached, ignoring deposit {}",
+                    row, max, tx_id
+                );
+                return Ok(false);
+            }
+        }
+        if let Some(max) = self.config.max_transactions_per_client {
+            if self.client_tx_index.get(&client).map_or(0, Vec::len) >= max {
+                warn!(
+                    "Row {}: Client {} reached the limit of {} stored transactions, ignoring deposit {}",
+                    row, client, max, tx_id
+                );
+                return Ok(false);
+            }
+        }
+
+        // Safety: ensure_account_exists was called before this method
+        let account = self.accounts.get_mut(&client).expect("account exists");
+
+        // Safety: the caller has already rejected locked accounts, so a
+        // failed deposit can only mean the balance would overflow
+        if !account.deposit(amount) {
+            return Err(EngineError::ArithmeticOverflow { tx_id, row });
+        }
+        account.record_event(row, AccountEvent::Deposited { tx_id, amount });
+
+        self.store_transaction(StoredTransaction {
+            row_idx: row,
+            ..StoredTransaction::from_deposit(tx_id, client, amount)
+        });
+        debug!("Row {}: Deposited {} to client {}", row, amount, client);
+
+        Ok(true)
+    }
+
+    /// Stores a deposit for later dispute reference.
+    fn store_transaction(&mut self, tx: StoredTransaction) {
+        let ids = self.client_tx_index.entry(tx.client).or_default();
+        if let Err(pos) = ids.binary_search(&tx.tx_id) {
+            ids.insert(pos, tx.tx_id);
+        }
+        self.transactions.insert(tx.tx_id, tx);
+    }
+
+    /// Processes a withdrawal transaction, returning the amount withdrawn and
+    /// the shortfall if it was applied.
+    ///
+    /// The shortfall is non-zero only for partial withdrawals.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, client),
+            fields(client_id = client, tx_type = "withdrawal")
+        )
+    )]
+    fn process_withdrawal(
+        &mut self,
+        tx_id: u32,
+        client: u16,
+        amount: Decimal4,
+        row: usize,
+    ) -> Result<Option<(Decimal4, Decimal4)>> {
+        if self.is_duplicate_tx(tx_id, row)? {
+            return Ok(None);
+        }
+        if amount.is_negative() {
+            warn!(
+                "Row {}: Negative withdrawal amount {}, ignoring",
+                row, amount
+            );
+            return Ok(None);
+        }
+        if amount.is_zero() && self.config.reject_zero_amounts {
+            warn!("Row {}: Zero withdrawal amount, ignoring", row);
+            return Ok(None);
+        }
+
+        // Safety: ensure_account_exists was called before this method
+        let account = self.accounts.get_mut(&client).expect("account exists");
+
+        if account.withdraw(amount) {
+            account.record_event(row, AccountEvent::Withdrew { tx_id, amount });
+            debug!("Row {}: Withdrew {} from client {}", row, amount, client);
+            Ok(Some((amount, Decimal4::ZERO)))
+        } else if account.can_withdraw(amount) {
+            Err(EngineError::ArithmeticOverflow { tx_id, row })
+        } else if self.config.partial_withdrawal_allowed && account.available > Decimal4::ZERO {
+            let actual = account.available;
+            if !account.withdraw(actual) {
+                return Err(EngineError::ArithmeticOverflow { tx_id, row });
+            }
+            account.record_event(
+                row,
+                AccountEvent::Withdrew {
+                    tx_id,
+                    amount: actual,
+                },
+            );
+            let shortfall = amount - actual;
+            warn!(
+                "Row {}: Client {}: Partial withdrawal: requested {}, withdrew {}, shortfall {}",
+                row, client, amount, actual, shortfall
+            );
+            Ok(Some((actual, shortfall)))
+        } else if self.config.strict_business_rules {
+            Err(EngineError::InsufficientFunds {
+                client,
+                available: account.available,
+                requested: amount,
+            })
+        } else {
+            debug!(
+                "Row {}: Withdrawal of {} from client {} failed (insufficient funds)",
+                row, amount, client
+            );
+            Ok(None)
+        }
+    }
+
+    /// Processes a dispute transaction.
+    ///
+    /// A dispute moves funds from available to held. If the client has withdrawn
+    /// funds after the disputed deposit, available may become negative.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, client),
+            fields(client_id = client, tx_type = "dispute")
+        )
+    )]
+    fn process_dispute(&mut self, tx_id: u32, client: u16, row: usize) -> Result<bool> {
+        let stored_tx = match self.transactions.get_mut(&tx_id) {
+            Some(tx) => tx,
+            None => {
+                debug!(
+                    "Row {}: Dispute references unknown transaction {}, ignoring",
+                    row, tx_id
+                );
+                return Ok(false);
+            }
+        };
+
+        if stored_tx.client != client {
+            let actual_client = stored_tx.client;
+            return self.reject_client_mismatch(tx_id, client, actual_client, "dispute", row);
+        }
+
+        if stored_tx.under_dispute {
+            debug!(
+                "Row {}: Transaction {} already under dispute, ignoring",
+                row, tx_id
+            );
+            return Ok(false);
+        }
+
+        let amount = stored_tx.amount;
+
+        // Safety: disputes reference stored transactions which require an existing account
+        let account = self
+            .accounts
+            .get_mut(&client)
+            .expect("account exists for stored tx");
+        if !self.config.allow_negative_available && account.available < amount {
+            warn!(
+                "Row {}: Dispute of transaction {} would make available negative, ignoring",
+                row, tx_id
+            );
+            return Ok(false);
+        }
+        if !account.hold(amount) {
+            return Err(EngineError::ArithmeticOverflow { tx_id, row });
+        }
+        account.add_held_tx(tx_id);
+        account.record_event(row, AccountEvent::DisputeOpened { tx_id });
+        stored_tx.under_dispute = true;
+        stored_tx.dispute_count = stored_tx.dispute_count.saturating_add(1);
+
+        debug!(
+            "Row {}: Disputed transaction {} for client {}, holding {}",
+            row, tx_id, client, amount
+        );
+
+        Ok(true)
+    }
+
+    /// Processes a resolve transaction.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, client),
+            fields(client_id = client, tx_type = "resolve")
+        )
+    )]
+    fn process_resolve(&mut self, tx_id: u32, client: u16, row: usize) -> Result<bool> {
+        let stored_tx = match self.transactions.get_mut(&tx_id) {
+            Some(tx) => tx,
+            None => {
+                debug!(
+                    "Row {}: Resolve references unknown transaction {}, ignoring",
+                    row, tx_id
+                );
+                return Ok(false);
+            }
+        };
+
+        if stored_tx.client != client {
+            let actual_client = stored_tx.client;
+            return self.reject_client_mismatch(tx_id, client, actual_client, "resolve", row);
+        }
+
+        if !stored_tx.under_dispute {
+            debug!(
+                "Row {}: Transaction {} not under dispute, ignoring resolve",
+                row, tx_id
+            );
+            return Ok(false);
+        }
+
+        let amount = stored_tx.amount;
+
+        // Safety: resolves reference stored transactions which require an existing account
+        let account = self
+            .accounts
+            .get_mut(&client)
+            .expect("account exists for stored tx");
+        if !account.release(amount) {
+            return Err(EngineError::ArithmeticOverflow { tx_id, row });
+        }
+        account.remove_held_tx(tx_id);
+        account.record_event(row, AccountEvent::DisputeResolved { tx_id });
+        stored_tx.under_dispute = false;
+
+        debug!(
+            "Row {}: Resolved dispute for transaction {} for client {}, released {}",
+            row, tx_id, client, amount
+        );
+
+        Ok(true)
+    }
+
+    /// Processes a chargeback transaction.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip(self, client),
+            fields(client_id = client, tx_type = "chargeback")
+        )
+    )]
+    fn process_chargeback(&mut self, tx_id: u32, client: u16, row: usize) -> Result<bool> {
+        let stored_tx = match self.transactions.get_mut(&tx_id) {
+            Some(tx) => tx,
+            None => {
+                debug!(
+                    "Row {}: Chargeback references unknown transaction {}, ignoring",
+                    row, tx_id
+                );
+                return Ok(false);
+            }
+        };
+
+        if stored_tx.client != client {
+            let actual_client = stored_tx.client;
+            return self.reject_client_mismatch(tx_id, client, actual_client, "chargeback", row);
+        }
+
+        if !stored_tx.under_dispute {
+            debug!(
+                "Row {}: Transaction {} not under dispute, ignoring chargeback",
+                row, tx_id
+            );
+            return Ok(false);
+        }
+
+        let amount = stored_tx.amount;
+
+        // Safety: chargebacks reference stored transactions which require an existing account
+        let account = self
+            .accounts
+            .get_mut(&client)
+            .expect("account exists for stored tx");
+        if !account.chargeback(amount) {
+            return Err(EngineError::ArithmeticOverflow { tx_id, row });
+        }
+        account.remove_held_tx(tx_id);
+        account.record_event(row, AccountEvent::ChargebackApplied { tx_id, amount });
+        account.record_event(row, AccountEvent::AccountLocked);
+        stored_tx.under_dispute = false;
+        stored_tx.charged_back = true;
+
+        debug!(
+            "Row {}: Chargeback for transaction {} for client {}, removed {}, account locked",
+            row, tx_id, client, amount
+        );
+
+        Ok(true)
+    }
+
+    /// Writes final account states to CSV.
+    ///
+    /// Output is sorted by client ID in ascending order for deterministic results.
+    /// All monetary values are formatted with exactly 4 decimal places.
+    pub fn write_output<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_output_with_delimiter(writer, b',')
+    }
+
+    /// Writes final account states to CSV preceded by a UTF-8 byte order mark.
+    ///
+    /// Excel on Windows needs the BOM to detect UTF-8. The content after the
+    /// three BOM bytes is identical to [`write_output`](Self::write_output).
+    pub fn write_output_with_bom<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(b"\xEF\xBB\xBF")?;
+        self.write_output(writer)
+    }
+
+    /// Returns final account states as CSV bytes.
+    ///
+    /// Same format as [`write_output`](Self::write_output).
+    pub fn write_output_to_vec(&self) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.write_output(&mut output)?;
+        Ok(output)
+    }
+
+    /// Returns final account states as a CSV string.
+    ///
+    /// Same format as [`write_output`](Self::write_output).
+    pub fn write_output_to_string(&self) -> Result<String> {
+        let output = self.write_output_to_vec()?;
+        debug_assert!(output.is_ascii(), "CSV output must be ASCII");
+        // SAFETY: the output only contains client IDs, decimals, booleans,
+        // commas and newlines, all of which are ASCII and therefore UTF-8.
+        Ok(unsafe { String::from_utf8_unchecked(output) })
+    }
+
+    /// Writes final account states as tab-separated values.
+    ///
+    /// Ordering and decimal formatting are identical to [`write_output`](Self::write_output).
+    /// No fields are quoted.
+    pub fn write_output_tsv<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_output_with_delimiter(writer, b'\t')
+    }
+
+    /// Writes final account states using the given field delimiter.
+    fn write_output_with_delimiter<W: Write>(&self, writer: W, delimiter: u8) -> Result<()> {
+        self.write_filtered_output(writer, delimiter, |_| true)
+    }
+
+    /// Writes account states matching `filter` as CSV.
+    ///
+    /// The header is always written; only accounts for which `filter` returns
+    /// `true` follow, sorted by client ID.
+    pub fn write_output_with_filter<W, F>(&self, writer: W, filter: F) -> Result<()>
+    where
+        W: Write,
+        F: Fn(&ClientAccount) -> bool,
+    {
+        self.write_filtered_output(writer, b',', filter)
+    }
+
+    /// Writes accounts that are new or changed since `baseline` as CSV, for
+    /// incremental syncs.
+    ///
+    /// The header is always written. An account is included if `baseline` has
+    /// no account for its client or any of `available`, `held`, `total` or
+    /// `locked` differ; rows are written in full. Accounts removed since the
+    /// baseline are not reported.
+    pub fn write_output_diff_from_snapshot<W: Write>(
+        &self,
+        writer: W,
+        baseline: &EngineSnapshot,
+    ) -> Result<()> {
+        self.write_output_with_filter(writer, |a| {
+            baseline
+                .accounts
+                .get(&a.client)
+                .is_none_or(|before| output_differs(a, before))
+        })
+    }
+
+    /// Writes only the accounts of `client_ids` as CSV, in ascending client ID
+    /// order, e.g. to shard output across files by client range.
+    ///
+    /// The header is always written. IDs without an account are skipped.
+    pub fn write_output_selecting_clients<W: Write>(
+        &self,
+        writer: W,
+        client_ids: &[u16],
+    ) -> Result<()> {
+        let selected: std::collections::HashSet<u16> = client_ids.iter().copied().collect();
+        self.write_output_with_filter(writer, |a| selected.contains(&a.client))
+    }
+
+    /// Writes only locked accounts as CSV.
+    pub fn write_locked_accounts<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_output_with_filter(writer, |a| a.locked)
+    }
+
+    /// Writes only accounts with a negative available balance as CSV.
+    pub fn write_negative_balance_accounts<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_output_with_filter(writer, |a| a.available.is_negative())
+    }
+
+    /// Writes account states as CSV, omitting inactive accounts.
+    ///
+    /// An account is inactive when its `total` and `held` are zero, it is not
+    /// locked and none of its stored deposits was ever disputed, e.g. one
+    /// opened by a withdrawal that failed for insufficient funds. The output
+    /// can be loaded with [`import_csv_accounts`](Self::import_csv_accounts).
+    pub fn write_output_compact<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_output_with_filter(writer, |a| {
+            !a.total.is_zero()
+                || !a.held.is_zero()
+                || a.locked
+                || self
+                    .transactions_for_client(a.client)
+                    .iter()
+                    .any(|tx| tx.dispute_count > 0)
+        })
+    }
+
+    /// Writes account states matching `filter` using the given field delimiter.
+    fn write_filtered_output<W, F>(&self, writer: W, delimiter: u8, filter: F) -> Result<()>
+    where
+        W: Write,
+        F: Fn(&ClientAccount) -> bool,
+    {
+        Self::write_accounts(writer, delimiter, &self.filtered_accounts(filter))
+    }
+
+    /// Writes final account states to CSV in the order given by `key_fn`.
+    ///
+    /// The header is always written first. Accounts with equal keys keep
+    /// ascending client ID order. For example, `|a| std::cmp::Reverse(a.total)`
+    /// lists the highest balances first.
+    pub fn write_output_sorted_by<W, K, F>(&self, writer: W, key_fn: F) -> Result<()>
+    where
+        W: Write,
+        K: Ord,
+        F: Fn(&ClientAccount) -> K,
+    {
+        let mut accounts = self.sorted_accounts();
+        accounts.sort_by_key(|a| key_fn(a));
+        Self::write_accounts(writer, b',', &accounts)
+    }
+
+    /// Writes the standard header followed by one row per account, in order.
+    fn write_accounts<W: Write>(
+        writer: W,
+        delimiter: u8,
+        accounts: &[&ClientAccount],
+    ) -> Result<()> {
+        let csv_writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(if delimiter == b'\t' {
+                csv::QuoteStyle::Never
+            } else {
+                csv::QuoteStyle::Necessary
+            })
+            .from_writer(writer);
+        Self::write_account_records(csv_writer, accounts)
+    }
+
+    /// Writes the standard header and account rows with a configured CSV writer.
+    fn write_account_records<W: Write>(
+        mut csv_writer: csv::Writer<W>,
+        accounts: &[&ClientAccount],
+    ) -> Result<()> {
+        csv_writer.write_record(["client", "available", "held", "total", "locked"])?;
+
+        for account in accounts {
+            csv_writer.write_record([
+                account.client.to_string(),
+                account.available.to_string(),
+                account.held.to_string(),
+                account.total.to_string(),
+                account.locked.to_string(),
+            ])?;
+        }
+
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes final account states as strict RFC 4180 CSV.
+    ///
+    /// Every field is enclosed in double quotes (embedded quotes are doubled)
+    /// and every record, including the last, ends with CRLF. Rows and values
+    /// are otherwise identical to [`write_output`](Self::write_output).
+    pub fn write_output_csv_rfc4180<W: Write>(&self, writer: W) -> Result<()> {
+        let csv_writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::Always)
+            .terminator(csv::Terminator::CRLF)
+            .double_quote(true)
+            .from_writer(writer);
+        Self::write_account_records(csv_writer, &self.sorted_accounts())
+    }
+
+    /// Writes final account states to CSV with lifetime flow columns.
+    ///
+    /// Same as [`write_output`](Self::write_output) with two extra columns,
+    /// `total_deposited` and `total_withdrawn`.
+    pub fn write_output_extended<W: Write>(&self, writer: W) -> Result<()> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+
+        csv_writer.write_record([
+            "client",
+            "available",
+            "held",
+            "total",
+            "locked",
+            "total_deposited",
+            "total_withdrawn",
+        ])?;
+
+        for account in self.sorted_accounts() {
+            csv_writer.write_record([
+                account.client.to_string(),
+                account.available.to_string(),
+                account.held.to_string(),
+                account.total.to_string(),
+                account.locked.to_string(),
+                account.total_deposited().to_string(),
+                account.total_withdrawn().to_string(),
+            ])?;
+        }
+
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns the audit log for a client, or `None` if the account does not exist.
+    #[cfg(feature = "audit_log")]
+    pub fn get_audit_log(&self, client: u16) -> Option<&[AccountEvent]> {
+        self.accounts.get(&client).map(|a| a.audit_log())
+    }
+
+    /// Writes every account's audit log as CSV.
+    ///
+    /// Columns are `client,event_type,tx_id,amount,timestamp_idx`, where
+    /// `timestamp_idx` is the processing row that produced the event. Accounts
+    /// are sorted by client ID and events appear in the order they occurred.
+    /// `tx_id` and `amount` are empty for events that do not have them.
+    #[cfg(feature = "audit_log")]
+    pub fn write_audit_log<W: Write>(&self, writer: W) -> Result<()> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+
+        csv_writer.write_record(["client", "event_type", "tx_id", "amount", "timestamp_idx"])?;
+
+        for account in self.sorted_accounts() {
+            for (row, event) in account.audit_entries() {
+                csv_writer.write_record([
+                    account.client.to_string(),
+                    event.event_type().to_string(),
+                    event.tx_id().map(|id| id.to_string()).unwrap_or_default(),
+                    event.amount().map(|a| a.to_string()).unwrap_or_default(),
+                    row.to_string(),
+                ])?;
+            }
+        }
+
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes final account states as a single-line JSON array.
+    ///
+    /// Accounts are sorted by client ID and use the same object layout as
+    /// [`write_output_ndjson`](Self::write_output_ndjson).
+    pub fn write_output_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer(&mut writer, &self.sorted_accounts())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes final account states as an indented JSON array for debugging.
+    ///
+    /// The schema is identical to [`write_output_json`](Self::write_output_json).
+    pub fn write_output_json_pretty<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, &self.sorted_accounts())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes final account states as fixed-width, space-separated columns.
+    ///
+    /// Each line holds `client`, `available`, `held`, `total` and `locked`,
+    /// sorted by client ID, with no header. Client IDs are padded to
+    /// `client_width` and amounts to `amount_width`, both right-aligned;
+    /// `locked` is padded to 5 characters. Nothing is written on error.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::InvalidArgument`] if `amount_width` is less than
+    /// 7, and [`EngineError::InvalidRecord`] if a value is wider than its
+    /// column, with `row` set to the 1-based output line.
+    pub fn write_output_fixed_width<W: Write>(
+        &self,
+        mut writer: W,
+        client_width: usize,
+        amount_width: usize,
+    ) -> Result<()> {
+        if amount_width < 7 {
+            return Err(EngineError::InvalidArgument(format!(
+                "amount width must be at least 7, got {amount_width}"
+            )));
+        }
+
+        let mut output = String::new();
+        for (idx, account) in self.sorted_accounts().into_iter().enumerate() {
+            let fields = [
+                (account.client.to_string(), client_width, "client"),
+                (account.available.to_string(), amount_width, "available"),
+                (account.held.to_string(), amount_width, "held"),
+                (account.total.to_string(), amount_width, "total"),
+            ];
+            for (value, width, column) in &fields {
+                if value.len() > *width {
+                    return Err(EngineError::InvalidRecord {
+                        row: idx + 1,
+                        message: format!("{column} value {value} exceeds width {width}"),
+                    });
+                }
+                output.push_str(&format!("{value:>width$} "));
+            }
+            output.push_str(&format!("{:>5}\n", account.locked));
+        }
+
+        writer.write_all(output.as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes final account states as newline-delimited JSON.
+    ///
+    /// Each account is written as one JSON object per line, sorted by client ID.
+    /// Monetary values are JSON strings with exactly 4 decimal places to avoid
+    /// floating-point precision loss in consumers.
+    pub fn write_output_ndjson<W: Write>(&self, mut writer: W) -> Result<()> {
+        for account in self.sorted_accounts() {
+            serde_json::to_writer(&mut writer, account)?;
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns accounts whose available balance is negative, sorted by client ID.
+    ///
+    /// This happens when a deposit is disputed after its funds were withdrawn.
+    pub fn accounts_with_negative_balance(&self) -> Vec<&ClientAccount> {
+        self.filtered_accounts(|a| a.available.is_negative())
+    }
+
+    /// Returns accounts with funds held by active disputes, sorted by client ID.
+    pub fn accounts_with_held_funds(&self) -> Vec<&ClientAccount> {
+        self.filtered_accounts(|a| !a.held.is_zero())
+    }
+
+    /// Returns the stored deposits of a client, sorted by transaction ID.
+    pub fn transactions_for_client(&self, client: u16) -> Vec<&StoredTransaction> {
+        self.client_tx_index
+            .get(&client)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| self.transactions.get(id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns stored deposits currently under dispute, sorted by transaction ID.
+    pub fn pending_disputes(&self) -> Vec<&StoredTransaction> {
+        let mut transactions: Vec<_> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.under_dispute)
+            .collect();
+        transactions.sort_by_key(|tx| tx.tx_id);
+        transactions
+    }
+
+    /// Resolves every open dispute, releasing the held funds, and returns the
+    /// number of disputes resolved.
+    ///
+    /// Intended for administrative clean-up such as shutting the system down.
+    /// Each resolution is counted like a processed resolve, notifies the
+    /// hooks and is recorded in the audit log with row 0. Disputes on locked
+    /// accounts are resolved as well, releasing their held funds; the
+    /// accounts stay locked.
+    pub fn resolve_all_disputes(&mut self) -> usize {
+        let mut disputed: Vec<u32> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.under_dispute)
+            .map(|tx| tx.tx_id)
+            .collect();
+        disputed.sort_unstable();
+
+        let mut resolved = 0;
+        for tx_id in disputed {
+            let stored_tx = self
+                .transactions
+                .get_mut(&tx_id)
+                .expect("disputed tx is stored");
+            let Some(account) = self.accounts.get_mut(&stored_tx.client) else {
+                continue;
+            };
+            if !account.release_ignoring_lock(stored_tx.amount) {
+                continue;
+            }
+            account.remove_held_tx(tx_id);
+            account.record_event(0, AccountEvent::DisputeResolved { tx_id });
+            stored_tx.under_dispute = false;
+            let tx = ParsedTransaction {
+                tx_id,
+                client: stored_tx.client,
+                kind: TxKind::Resolve,
+            };
+            self.record_applied(&tx);
+            resolved += 1;
+        }
+        resolved
+    }
+
+    /// Returns the balances `client` would have if chargebacks were processed
+    /// for all of its open disputes, or `None` if it has no account.
+    ///
+    /// Chargebacks are simulated in transaction ID order on a copy of the
+    /// account, so the engine is not modified. As with real processing, the
+    /// first chargeback locks the account and later ones would be ignored.
+    pub fn simulate_chargeback_impact(&self, client: u16) -> Option<SimulatedChargebackResult> {
+        let mut account = self.accounts.get(&client)?.clone();
+        for tx in self.transactions_for_client(client) {
+            if tx.under_dispute {
+                account.chargeback(tx.amount);
+            }
+        }
+        Some(SimulatedChargebackResult {
+            resulting_available: account.available,
+            resulting_held: account.held,
+            resulting_total: account.total,
+            would_be_locked: account.locked,
+        })
+    }
+
+    /// Returns stored deposits disputed at least `threshold` times, sorted by
+    /// transaction ID.
+    pub fn highly_disputed_transactions(&self, threshold: u32) -> Vec<&StoredTransaction> {
+        let mut transactions: Vec<_> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.dispute_count >= threshold)
+            .collect();
+        transactions.sort_by_key(|tx| tx.tx_id);
+        transactions
+    }
+
+    /// Returns stored transactions whose client has no account, sorted by
+    /// transaction ID.
+    ///
+    /// Always empty for state built through this type; a non-empty result
+    /// points to externally corrupted state.
+    pub fn find_orphan_transactions(&self) -> Vec<&StoredTransaction> {
+        let mut orphans: Vec<_> = self
+            .transactions
+            .values()
+            .filter(|tx| !self.accounts.contains_key(&tx.client))
+            .collect();
+        orphans.sort_by_key(|tx| tx.tx_id);
+        orphans
+    }
+
+    /// Returns accounts holding funds without any stored transaction under
+    /// dispute, sorted by client ID.
+    ///
+    /// Accounts restored with a `held` balance but without their disputed
+    /// deposits are reported here.
+    pub fn find_unknown_held_funds(&self) -> Vec<&ClientAccount> {
+        self.filtered_accounts(|a| {
+            a.held > Decimal4::ZERO
+                && !self
+                    .transactions_for_client(a.client)
+                    .iter()
+                    .any(|tx| tx.under_dispute)
+        })
+    }
+
+    /// Checks the consistency of all accounts and stored transactions.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::InvalidAccount`] for the first problem found:
+    /// an account whose `total` does not equal `available + held`, a stored
+    /// transaction without an account (see
+    /// [`find_orphan_transactions`](Self::find_orphan_transactions)), or held
+    /// funds without a disputed transaction (see
+    /// [`find_unknown_held_funds`](Self::find_unknown_held_funds)).
+    pub fn verify_all_invariants(&self) -> Result<()> {
+        if let Some((client, message)) = self
+            .sorted_accounts()
+            .into_iter()
+            .find_map(|a| balance_mismatch(a).map(|message| (a.client, message)))
+        {
+            return Err(EngineError::InvalidAccount { client, message });
+        }
+        if let Some(tx) = self.find_orphan_transactions().first() {
+            return Err(EngineError::InvalidAccount {
+                client: tx.client,
+                message: format!("no account for stored transaction {}", tx.tx_id),
+            });
+        }
+        if let Some(account) = self.find_unknown_held_funds().first() {
+            return Err(EngineError::InvalidAccount {
+                client: account.client,
+                message: format!("held {} without a disputed transaction", account.held),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks every link between accounts and stored transactions and lists
+    /// all violations, e.g. for assertions after processing.
+    ///
+    /// Unlike [`verify_all_invariants`](Self::verify_all_invariants), which
+    /// stops at the first problem, this reports stored transactions without
+    /// an account, accounts whose `held` differs from the sum of their
+    /// disputed deposits, accounts where `total != available + held`,
+    /// transaction IDs indexed more than once, and lifetime deposits minus
+    /// withdrawals and chargebacks that differ from
+    /// [`total_funds`](Self::total_funds). The lifetime check is skipped once
+    /// accounts were restored without their history, e.g. via
+    /// [`import_csv_accounts`](Self::import_csv_accounts).
+    pub fn run_integrity_check(&self) -> IntegrityReport {
+        let mut violations: Vec<IntegrityViolation> = self
+            .find_orphan_transactions()
+            .into_iter()
+            .map(|tx| IntegrityViolation::OrphanTransaction {
+                tx_id: tx.tx_id,
+                client: tx.client,
+            })
+            .collect();
+
+        let mut disputed: HashMap<u16, Decimal4> = HashMap::new();
+        for tx in self.transactions.values().filter(|tx| tx.under_dispute) {
+            let sum = disputed.entry(tx.client).or_default();
+            *sum = sum.saturating_add(tx.amount);
+        }
+        for account in self.sorted_accounts() {
+            let disputed = disputed.get(&account.client).copied().unwrap_or_default();
+            if account.held != disputed {
+                violations.push(IntegrityViolation::HeldMismatch {
+                    client: account.client,
+                    held: account.held,
+                    disputed,
+                });
+            }
+        }
+
+        for account in self.sorted_accounts() {
+            if !account.balances_consistent() {
+                violations.push(IntegrityViolation::BalanceMismatch {
+                    client: account.client,
+                    available: account.available,
+                    held: account.held,
+                    total: account.total,
+                });
+            }
+        }
+
+        let mut occurrences: HashMap<u32, usize> = HashMap::new();
+        for tx_id in self.client_tx_index.values().flatten() {
+            *occurrences.entry(*tx_id).or_default() += 1;
+        }
+        let mut duplicates: Vec<_> = occurrences.into_iter().filter(|&(_, n)| n > 1).collect();
+        duplicates.sort_unstable();
+        violations.extend(
+            duplicates
+                .into_iter()
+                .map(|(tx_id, occurrences)| IntegrityViolation::DuplicateTxId {
+                    tx_id,
+                    occurrences,
+                }),
+        );
+
+        let total_funds = self.total_funds();
+        let opening_balances: Decimal4 = self.accounts.values().map(|a| a.opening_balance()).sum();
+        let deposited = self.total_deposited_ever();
+        let withdrawn = self.total_withdrawn_ever();
+        let charged_back = self.total_chargebacked();
+        let expected = opening_balances
+            .checked_add(deposited)
+            .and_then(|sum| sum.checked_sub(withdrawn))
+            .and_then(|net| net.checked_sub(charged_back));
+        if expected != Some(total_funds) {
+            violations.push(IntegrityViolation::LifetimeTotalsMismatch {
+                opening_balances,
+                deposited,
+                withdrawn,
+                charged_back,
+                total_funds,
+            });
+        }
+
+        IntegrityReport {
+            passed: violations.is_empty(),
+            violations,
+        }
+    }
+
+    /// Removes repeated transaction IDs from the per-client transaction index
+    /// and returns how many entries were removed.
+    ///
+    /// Stored transactions are keyed by ID and cannot repeat, but the index
+    /// can after manual state manipulation. Each ID is kept once: under the
+    /// client that owns the stored transaction if it is listed there,
+    /// otherwise under the lowest client ID listing it.
+    pub fn deduplicate_transactions(&mut self) -> usize {
+        use std::collections::hash_map::Entry;
+
+        let mut clients: Vec<u16> = self.client_tx_index.keys().copied().collect();
+        clients.sort_unstable();
+
+        let mut keepers: HashMap<u32, u16> = HashMap::new();
+        for client in clients {
+            for &tx_id in &self.client_tx_index[&client] {
+                let owned = self
+                    .transactions
+                    .get(&tx_id)
+                    .is_some_and(|tx| tx.client == client);
+                match keepers.entry(tx_id) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(client);
+                    }
+                    Entry::Occupied(mut entry) if owned => {
+                        entry.insert(client);
+                    }
+                    Entry::Occupied(_) => {}
+                }
+            }
+        }
+
+        let mut removed = 0;
+        for (client, tx_ids) in &mut self.client_tx_index {
+            let before = tx_ids.len();
+            tx_ids.sort_unstable();
+            tx_ids.dedup();
+            tx_ids.retain(|tx_id| keepers[tx_id] == *client);
+            removed += before - tx_ids.len();
+        }
+        removed
+    }
+
+    /// Counts accounts by `available` balance.
+    ///
+    /// `buckets` holds sorted boundaries `b0 < b1 < … < bn`. The result has
+    /// `n + 2` entries counting balances in `(-∞, b0)`, `[b0, b1)`, …, `[bn, +∞)`.
+    pub fn account_balance_histogram(&self, buckets: &[Decimal4]) -> Vec<usize> {
+        let mut counts = vec![0; buckets.len() + 1];
+        for account in self.accounts.values() {
+            counts[buckets.partition_point(|&b| b <= account.available)] += 1;
+        }
+        counts
+    }
+
+    /// Returns the `n` accounts with the highest `total`, highest first.
+    ///
+    /// Ties are broken by ascending client ID. Runs in O(N) on average plus
+    /// O(n log n) to order the result.
+    pub fn top_n_accounts_by_total(&self, n: usize) -> Vec<&ClientAccount> {
+        self.first_n_accounts_by(n, |a, b| b.total.cmp(&a.total))
+    }
+
+    /// Returns the `n` accounts with the lowest `available`, lowest first.
+    ///
+    /// Ties are broken by ascending client ID. Runs in O(N) on average plus
+    /// O(n log n) to order the result.
+    pub fn bottom_n_accounts_by_available(&self, n: usize) -> Vec<&ClientAccount> {
+        self.first_n_accounts_by(n, |a, b| a.available.cmp(&b.available))
+    }
+
+    /// Returns the first `n` accounts under `cmp` (then client ID) using a
+    /// partial sort.
+    fn first_n_accounts_by<F>(&self, n: usize, cmp: F) -> Vec<&ClientAccount>
+    where
+        F: Fn(&ClientAccount, &ClientAccount) -> Ordering,
+    {
+        let cmp = |a: &&ClientAccount, b: &&ClientAccount| cmp(a, b).then(a.client.cmp(&b.client));
+        let mut accounts: Vec<&ClientAccount> = self.accounts.values().collect();
+        if n == 0 {
+            return Vec::new();
+        }
+        if n < accounts.len() {
+            accounts.select_nth_unstable_by(n - 1, cmp);
+            accounts.truncate(n);
+        }
+        accounts.sort_unstable_by(cmp);
+        accounts
+    }
+
+    /// Returns locked (charged back) accounts, sorted by client ID.
+    pub fn locked_accounts(&self) -> Vec<&ClientAccount> {
+        self.filtered_accounts(|a| a.locked)
+    }
+
+    /// Returns accounts matching `predicate`, sorted by client ID.
+    fn filtered_accounts<F>(&self, predicate: F) -> Vec<&ClientAccount>
+    where
+        F: Fn(&ClientAccount) -> bool,
+    {
+        let mut accounts = self.sorted_accounts();
+        accounts.retain(|a| predicate(a));
+        accounts
+    }
+
+    /// Removes all accounts and stored transactions, keeping allocated capacity.
+    ///
+    /// Configuration, hooks and transaction counters are preserved; use
+    /// [`reset`](Self::reset) to also zero the counters.
+    pub fn clear(&mut self) {
+        self.accounts.clear();
+        self.transactions.clear();
+        self.client_tx_index.clear();
+        self.flushed_tx_ids.clear();
+        self.stats.clear();
+    }
+
+    /// Removes a single account and its stored transactions, returning the account.
+    pub fn drain_account(&mut self, client: u16) -> Option<ClientAccount> {
+        let account = self.accounts.remove(&client)?;
+        self.stats.remove(&client);
+        for tx_id in self.client_tx_index.remove(&client).unwrap_or_default() {
+            self.transactions.remove(&tx_id);
+        }
+        Some(account)
+    }
+
+    /// Splits the engine into `(active, locked)` engines holding the unlocked
+    /// and locked accounts respectively, each with its stored transactions
+    /// and statistics.
+    ///
+    /// Both engines share the configuration and logical clock. The active
+    /// engine keeps the hooks and the transaction counters used by
+    /// [`report`](Self::report); the locked engine starts with neither.
+    pub fn partition_by_lock_status(mut self) -> (PaymentsEngine, PaymentsEngine) {
+        let mut locked = PaymentsEngine::with_config(self.config.clone());
+        locked.logical_clock = self.logical_clock;
+        locked.flushed_tx_ids = self.flushed_tx_ids.clone();
+
+        let locked_clients: Vec<u16> = self
+            .accounts
+            .values()
+            .filter(|a| a.locked)
+            .map(|a| a.client)
+            .collect();
+        for client in locked_clients {
+            self.move_client_to(client, &mut locked);
+        }
+
+        (self, locked)
+    }
+
+    /// Moves a client's account, stored transactions, statistics and
+    /// timeline into `other`, which must not already hold that client.
+    fn move_client_to(&mut self, client: u16, other: &mut PaymentsEngine) {
+        if let Some(account) = self.accounts.remove(&client) {
+            other.accounts.insert(client, account);
+        }
+        if let Some(stats) = self.stats.remove(&client) {
+            other.stats.insert(client, stats);
+        }
+        if let Some(tx_ids) = self.client_tx_index.remove(&client) {
+            for tx_id in &tx_ids {
+                if let Some(tx) = self.transactions.remove(tx_id) {
+                    other.transactions.insert(*tx_id, tx);
+                }
+            }
+            other.client_tx_index.insert(client, tx_ids);
+        }
+    }
+
+    /// Deletes a client's account and stored transactions, e.g. for a data
+    /// deletion request. Returns `Ok(None)` if the client has no account.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::ActiveDisputes`] without removing anything if
+    /// the account holds funds. `count` is the number of transactions under
+    /// dispute, which is zero for funds held by a restored account.
+    pub fn remove_account(&mut self, client: u16) -> Result<Option<ClientAccount>> {
+        if let Some(account) = self.accounts.get(&client) {
+            if !account.held.is_zero() {
+                return Err(EngineError::ActiveDisputes {
+                    client,
+                    count: account.held_transaction_ids().len(),
+                });
+            }
+        }
+        Ok(self.drain_account(client))
+    }
+
+    /// Removes all locked accounts and their stored transactions, returning
+    /// the accounts sorted by client ID.
+    ///
+    /// Useful for archiving frozen accounts in long-running processes.
+    pub fn drain_locked_accounts(&mut self) -> Vec<ClientAccount> {
+        let locked: Vec<u16> = self.locked_accounts().iter().map(|a| a.client).collect();
+        locked
+            .into_iter()
+            .filter_map(|client| self.drain_account(client))
+            .collect()
+    }
+
+    /// Clears all state, including transaction counters used by [`report`](Self::report).
+    pub fn reset(&mut self) {
+        self.clear();
+        self.counts = TransactionCounts::default();
+        self.logical_clock = 0;
+    }
+
+    /// Returns an approximate number of heap bytes used by accounts and stored
+    /// transactions.
+    ///
+    /// See [`estimated_memory_for`](Self::estimated_memory_for) for how the
+    /// estimate is computed and its caveats.
+    pub fn estimate_memory_usage(&self) -> usize {
+        Self::estimated_memory_for(self.accounts.len(), self.transactions.len())
+    }
+
+    /// Estimates heap bytes for an engine holding `clients` accounts and
+    /// `transactions` stored deposits.
+    ///
+    /// Each map entry is counted as its key and value plus one control byte,
+    /// scaled by the 7/8 maximum load factor of `std::collections::HashMap`.
+    /// The per-client transaction index adds one `u32` per stored transaction.
+    ///
+    /// This is a lower bound for capacity planning, not an exact figure:
+    /// maps grow in powers of two, so right after a resize up to roughly half
+    /// the buckets are empty and actual usage can be nearly twice the estimate.
+    /// Maps also never shrink after accounts are drained, and the held
+    /// transaction lists and audit logs inside accounts are not counted.
+    /// Estimates too large for `usize` saturate at `usize::MAX`.
+    pub fn estimated_memory_for(clients: usize, transactions: usize) -> usize {
+        fn map_bytes<K, V>(entries: usize) -> usize {
+            let bytes = entries.saturating_mul(std::mem::size_of::<(K, V)>() + 1);
+            bytes.saturating_add(bytes / 7)
+        }
+
+        map_bytes::<u16, ClientAccount>(clients)
+            .saturating_add(map_bytes::<u32, StoredTransaction>(transactions))
+            .saturating_add(map_bytes::<u16, Vec<u32>>(clients))
+            .saturating_add(transactions.saturating_mul(std::mem::size_of::<u32>()))
+    }
+
+    /// Returns an iterator over all accounts in unspecified order.
+    pub fn accounts_iter(&self) -> impl Iterator<Item = &ClientAccount> {
+        self.accounts.values()
+    }
+
+    /// Returns the sum of `total` across all accounts, saturating at
+    /// [`Decimal4::MAX_REPRESENTABLE`].
+    pub fn total_funds(&self) -> Decimal4 {
+        self.accounts.values().map(|a| a.total).sum()
+    }
+
+    /// Returns the current logical clock: the number of transactions applied
+    /// since the engine was created or [`reset`](Self::reset).
+    pub fn logical_clock(&self) -> u64 {
+        self.logical_clock
+    }
+
+    /// Returns accounts changed by a transaction applied after `clock`,
+    /// sorted by client ID.
+    ///
+    /// Pass the value of [`logical_clock`](Self::logical_clock) from a
+    /// previous sync to fetch only the accounts modified since then.
+    pub fn accounts_changed_since(&self, clock: u64) -> Vec<&ClientAccount> {
+        self.filtered_accounts(|a| a.last_modified_at > clock)
+    }
+
+    /// Returns activity statistics for a client, or `None` if it has no account.
+    ///
+    /// Accounts restored without history report zero counts.
+    pub fn account_stats(&self, client: u16) -> Option<AccountStats> {
+        self.accounts.get(&client)?;
+        Some(
+            self.stats
+                .get(&client)
+                .cloned()
+                .unwrap_or_else(|| AccountStats::new(client)),
+        )
+    }
+
+    /// Returns the operations applied to a client's account in processing
+    /// order, built from its audit log, or an empty list if it has none.
+    ///
+    /// Accounts restored without history have an empty timeline.
+    #[cfg(feature = "audit_log")]
+    pub fn transaction_timeline_for_client(&self, client: u16) -> Vec<TransactionEvent> {
+        let Some(account) = self.accounts.get(&client) else {
+            return Vec::new();
+        };
+        account
+            .audit_entries()
+            .filter_map(|(row_idx, event)| {
+                TransactionEvent::from_account_event(event, row_idx, |tx_id| {
+                    self.transactions
+                        .get(&tx_id)
+                        .map_or(Decimal4::ZERO, |stored| stored.amount)
+                })
+            })
+            .collect()
+    }
+
+    /// Returns activity statistics for every account, sorted by client ID.
+    pub fn all_account_stats(&self) -> Vec<AccountStats> {
+        self.sorted_accounts()
+            .iter()
+            .filter_map(|a| self.account_stats(a.client))
+            .collect()
+    }
+
+    /// Returns the sum of all deposits ever applied, from each account's
+    /// [`total_deposited`](ClientAccount::total_deposited).
+    ///
+    /// Together with the accounts' opening balances,
+    /// [`total_withdrawn_ever`](Self::total_withdrawn_ever) and
+    /// [`total_chargebacked`](Self::total_chargebacked) this accounts for
+    /// [`total_funds`](Self::total_funds). Deposits of accounts that are
+    /// drained or moved to another engine go with them. The sum saturates at
+    /// [`Decimal4::MAX_REPRESENTABLE`].
+    pub fn total_deposited_ever(&self) -> Decimal4 {
+        self.accounts.values().map(|a| a.total_deposited()).sum()
+    }
+
+    /// Returns the sum of all withdrawals ever applied, from each account's
+    /// [`total_withdrawn`](ClientAccount::total_withdrawn).
+    ///
+    /// See [`total_deposited_ever`](Self::total_deposited_ever).
+    pub fn total_withdrawn_ever(&self) -> Decimal4 {
+        self.accounts.values().map(|a| a.total_withdrawn()).sum()
+    }
+
+    /// Returns the sum of all chargebacks applied across accounts.
+    ///
+    /// Accounts restored via [`from_accounts`](Self::from_accounts) or
+    /// [`import_csv_accounts`](Self::import_csv_accounts) carry no chargeback
+    /// history and contribute nothing.
+    pub fn total_chargebacked(&self) -> Decimal4 {
+        self.accounts.values().map(|a| a.total_charged_back()).sum()
+    }
+
+    /// Returns the number of locked (charged back) accounts.
+    pub fn charged_back_client_count(&self) -> usize {
+        self.accounts.values().filter(|a| a.locked).count()
+    }
+
+    /// Builds a summary report of the current accounts and applied transactions.
+    pub fn report(&self) -> EngineReport {
+        EngineReport {
+            total_clients: self.accounts.len(),
+            locked_clients: self.accounts.values().filter(|a| a.locked).count(),
+            total_deposits: self.counts.deposits,
+            total_withdrawals: self.counts.withdrawals,
+            total_disputes_resolved: self.counts.resolves,
+            total_chargebacks: self.counts.chargebacks,
+            total_funds: self.total_funds(),
+            total_held: self.accounts.values().map(|a| a.held).sum(),
+            negative_balance_clients: self
+                .accounts
+                .values()
+                .filter(|a| a.available.is_negative())
+                .count(),
+        }
+    }
+
+    /// Compares this engine's accounts with another's.
+    ///
+    /// `added` holds accounts only in `other`, `removed` those only in `self`,
+    /// and `changed` those in both whose balances or lock state differ.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> EngineDiff<'a> {
+        let mut diff = EngineDiff::default();
+
+        for account in self.sorted_accounts() {
+            match other.accounts.get(&account.client) {
+                None => diff.removed.push(account),
+                Some(theirs) => {
+                    if output_differs(account, theirs) {
+                        diff.changed.push((account, theirs));
+                    }
+                }
+            }
+        }
+        for account in other.sorted_accounts() {
+            if !self.accounts.contains_key(&account.client) {
+                diff.added.push(account);
+            }
+        }
+
+        diff
+    }
+
+    /// Returns all accounts sorted by client ID for deterministic output.
+    fn sorted_accounts(&self) -> Vec<&ClientAccount> {
+        let mut accounts: Vec<_> = self.accounts.values().collect();
+        accounts.sort_by_key(|a| a.client);
+        accounts
+    }
+
+    /// Returns a client's account, or `None` if it has none.
+    pub fn get_account(&self, client_id: u16) -> Option<&ClientAccount> {
+        self.accounts.get(&client_id)
+    }
+
+    /// Returns a mutable reference to a client's account, or `None` if it has none.
+    ///
+    /// Use with care — mutating an account directly bypasses the engine's
+    /// invariant checks. Call [`verify_all_invariants`](Self::verify_all_invariants)
+    /// afterwards to detect inconsistent state.
+    #[must_use]
+    pub fn get_account_mut(&mut self, client_id: u16) -> Option<&mut ClientAccount> {
+        self.accounts.get_mut(&client_id)
+    }
+}
+
+impl Default for PaymentsEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Describes how `account` breaks `total == available + held`, or returns
+/// `None` if it holds. An overflowing sum is reported rather than panicking.
+fn balance_mismatch(account: &ClientAccount) -> Option<String> {
+    match account.available.checked_add(account.held) {
+        Some(sum) if sum == account.total => None,
+        Some(_) => Some(format!(
+            "total {} does not equal available {} + held {}",
+            account.total, account.available, account.held
+        )),
+        None => Some("available + held overflows".to_string()),
+    }
+}
+
+/// Returns `true` if two accounts would produce different output rows.
+fn output_differs(a: &ClientAccount, b: &ClientAccount) -> bool {
+    a.available != b.available || a.held != b.held || a.total != b.total || a.locked != b.locked
+}
+
+impl Clone for PaymentsEngine {
+    /// Clones the engine's accounts, stored transactions, configuration and counters.
+    ///
+    /// Hooks are not cloned: the copy uses [`NoopHooks`] so that processing on a
+    /// snapshot does not trigger side effects a second time.
+    fn clone(&self) -> Self {
+        PaymentsEngine {
+            accounts: self.accounts.clone(),
+            transactions: self.transactions.clone(),
+            client_tx_index: self.client_tx_index.clone(),
+            flushed_tx_ids: self.flushed_tx_ids.clone(),
+            config: self.config.clone(),
+            hooks: Box::new(NoopHooks),
+            counts: self.counts,
+            stats: self.stats.clone(),
+            logical_clock: self.logical_clock,
+        }
+    }
+}
+
+impl PartialEq for PaymentsEngine {
+    /// Two engines are equal when their accounts and stored transactions match.
+    fn eq(&self, other: &Self) -> bool {
+        self.accounts == other.accounts && self.transactions == other.transactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    fn process_csv_str(csv: &str) -> PaymentsEngine {
+        let mut engine = PaymentsEngine::new();
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        engine
+    }
+
+    /// Stored transactions with `row_idx` cleared, for comparing engines fed
+    /// the same transactions in different batches.
+    fn stored_ignoring_rows(engine: &PaymentsEngine) -> HashMap<u32, StoredTransaction> {
+        let mut transactions = engine.snapshot().transactions;
+        for tx in transactions.values_mut() {
+            tx.row_idx = 0;
+        }
+        transactions
+    }
+
+    #[test]
+    fn test_simple_deposits() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,5.0
+deposit,2,3,20.0"#;
+
+        let engine = process_csv_str(csv);
+
+        let acc1 = engine.get_account(1).unwrap();
+        assert_eq!(acc1.available.to_string(), "15.0000");
+        assert_eq!(acc1.total.to_string(), "15.0000");
+
+        let acc2 = engine.get_account(2).unwrap();
+        assert_eq!(acc2.available.to_string(), "20.0000");
+    }
+
+    #[test]
+    fn test_withdrawal() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,3.0"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.available.to_string(), "7.0000");
+        assert_eq!(acc.total.to_string(), "7.0000");
+    }
+
+    #[test]
+    fn test_withdrawal_insufficient_funds() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,15.0"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.available.to_string(), "10.0000");
+    }
+
+    #[test]
+    fn test_dispute_resolve() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+dispute,1,1,
+resolve,1,1,"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.available.to_string(), "10.0000");
+        assert_eq!(acc.held.to_string(), "0.0000");
+        assert!(!acc.locked);
+    }
+
+    #[test]
+    fn test_dispute_chargeback() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+dispute,1,1,
+chargeback,1,1,"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.available.to_string(), "0.0000");
+        assert_eq!(acc.held.to_string(), "0.0000");
+        assert_eq!(acc.total.to_string(), "0.0000");
+        assert!(acc.locked);
+    }
+
+    #[test]
+    fn test_held_transaction_ids_track_disputes() {
+        let csv = r#"type,client,tx,amount
+deposit,1,5,10.0
+deposit,1,2,5.0
+deposit,1,9,1.0
+dispute,1,9,
+dispute,1,2,
+dispute,1,5,
+resolve,1,2,"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.held_transaction_ids(), &[5, 9]);
+        assert_eq!(acc.held.to_string(), "11.0000");
+
+        let csv = format!("{}\nchargeback,1,5,", csv);
+        let engine = process_csv_str(&csv);
+        assert_eq!(engine.get_account(1).unwrap().held_transaction_ids(), &[9]);
+    }
+
+    #[test]
+    fn test_process_csv_strict_applies_valid_input() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,50.0
+withdrawal,1,3,4.0"#;
+
+        let mut engine = PaymentsEngine::new();
+        engine.process_csv_strict(Cursor::new(csv)).unwrap();
+        assert_eq!(
+            engine.get_account(1).unwrap().available.to_string(),
+            "6.0000"
+        );
+    }
+
+    #[test]
+    fn test_process_csv_strict_rejects_invalid_row_atomically() {
+        let mut engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,10.0");
+
+        let csv = r#"type,client,tx,amount
+deposit,1,2,5.0
+deposit,2,3,
+deposit,3,4,1.0"#;
+        let err = engine.process_csv_strict(Cursor::new(csv)).unwrap_err();
+        match err {
+            EngineError::InvalidRecord { row, message } => {
+                assert_eq!(row, 3);
+                assert_eq!(message, "Missing amount for deposit (column 'amount')");
+            }
+            other => panic!("Expected InvalidRecord, got {:?}", other),
+        }
+
+        let csv = r#"type,client,tx,amount
+deposit,1,2,5.0
+deposit,x,3,1.0"#;
+        let err = engine.process_csv_strict(Cursor::new(csv)).unwrap_err();
+        assert!(matches!(err, EngineError::CsvPositionError { line: 3, .. }));
+
+        assert_eq!(
+            engine.get_account(1).unwrap().available.to_string(),
+            "10.0000"
+        );
+        assert!(engine.get_account(2).is_none());
+        assert!(!engine.transactions.contains_key(&2));
+    }
+
+    #[test]
+    fn test_process_csv_strict_rolls_back_on_overflow() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,7922816251426433759354395.0335
+deposit,2,3,1.0"#;
+
+        let mut engine = PaymentsEngine::new();
+        let err = engine.process_csv_strict(Cursor::new(csv)).unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::ArithmeticOverflow { tx_id: 3, row: 4 }
+        ));
+        assert!(engine.accounts.is_empty());
+        assert!(engine.transactions.is_empty());
+    }
+
+    #[cfg(feature = "audit_log")]
+    #[test]
+    fn test_audit_log_records_applied_events() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,3.0
+withdrawal,1,3,30.0
+dispute,1,1,
+resolve,1,1,
+dispute,1,1,
+chargeback,1,1,
+deposit,1,4,1.0"#;
+
+        let engine = process_csv_str(csv);
+        let dec = |s: &str| Decimal4::from_str(s).unwrap();
+        assert_eq!(
+            engine.get_account(1).unwrap().audit_log(),
+            &[
+                AccountEvent::Deposited {
+                    tx_id: 1,
+                    amount: dec("10.0")
+                },
+                AccountEvent::Withdrew {
+                    tx_id: 2,
+                    amount: dec("3.0")
+                },
+                AccountEvent::DisputeOpened { tx_id: 1 },
+                AccountEvent::DisputeResolved { tx_id: 1 },
+                AccountEvent::DisputeOpened { tx_id: 1 },
+                AccountEvent::ChargebackApplied {
+                    tx_id: 1,
+                    amount: dec("10.0")
+                },
+                AccountEvent::AccountLocked,
+            ]
+        );
+    }
+
+    #[cfg(feature = "audit_log")]
+    #[test]
+    fn test_write_audit_log() {
+        let csv = r#"type,client,tx,amount
+deposit,2,1,5.0
+deposit,1,2,10.0
+dispute,2,1,
+withdrawal,1,3,2.5
+chargeback,2,1,"#;
+
+        let engine = process_csv_str(csv);
+        assert_eq!(engine.get_audit_log(1).unwrap().len(), 2);
+        assert!(engine.get_audit_log(3).is_none());
+
+        let mut output = Vec::new();
+        engine.write_audit_log(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "client,event_type,tx_id,amount,timestamp_idx\n\
+             1,deposited,2,10.0000,3\n\
+             1,withdrew,3,2.5000,5\n\
+             2,deposited,1,5.0000,2\n\
+             2,dispute_opened,1,,4\n\
+             2,chargeback_applied,1,5.0000,6\n\
+             2,account_locked,,,6\n"
+        );
+    }
+
+    const RESTORE_INPUT: &str = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,5.0
+deposit,2,3,7.0
+withdrawal,1,4,3.0
+dispute,1,2,
+"#;
+
+    const RESTORE_FOLLOW_UP: &str = r#"type,client,tx,amount
+resolve,1,2,
+dispute,2,3,
+chargeback,2,3,
+withdrawal,1,5,1.0
+"#;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut original = process_csv_str(RESTORE_INPUT);
+        let mut restored = PaymentsEngine::from_snapshot(original.snapshot()).unwrap();
+        assert!(original == restored);
+        assert_eq!(original.snapshot(), restored.snapshot());
+
+        original
+            .process_csv_bytes(RESTORE_FOLLOW_UP.as_bytes())
+            .unwrap();
+        restored
+            .process_csv_bytes(RESTORE_FOLLOW_UP.as_bytes())
+            .unwrap();
+        assert!(original == restored);
+        assert!(restored.get_account(2).unwrap().locked);
+    }
+
+    #[test]
+    fn test_snapshot_serde_round_trip() {
+        let original = process_csv_str(RESTORE_INPUT);
+        let snapshot = original.snapshot_with_offset(42);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: EngineSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.client_tx_index, snapshot.client_tx_index);
+        assert_eq!(decoded.offset, 42);
+
+        let restored = PaymentsEngine::from_snapshot(decoded).unwrap();
+        assert_eq!(
+            restored.write_output_to_string().unwrap(),
+            original.write_output_to_string().unwrap()
+        );
+        assert_eq!(
+            restored.transactions_for_client(1),
+            original.transactions_for_client(1)
+        );
+
+        // Snapshots written before the later fields existed still load.
+        let older = r#"{
+            "accounts": {"1": {"client": 1, "available": "5.0", "held": "0", "total": "5.0", "locked": false}},
+            "transactions": {"7": {"tx_id": 7, "client": 1, "amount": "5.0", "under_dispute": false}},
+            "client_tx_index": {"1": [7]}
+        }"#;
+        let decoded: EngineSnapshot = serde_json::from_str(older).unwrap();
+        assert_eq!(decoded.offset, 0);
+        assert_eq!(
+            decoded.transactions[&7],
+            StoredTransaction::from_deposit(7, 1, Decimal4::from_str("5.0").unwrap())
+        );
+        assert!(PaymentsEngine::from_snapshot(decoded).is_ok());
+    }
+
+    #[test]
+    fn test_from_snapshot_rejects_mismatched_index() {
+        let original = process_csv_str(RESTORE_INPUT);
+
+        let mut snapshot = original.snapshot();
+        snapshot.client_tx_index.get_mut(&1).unwrap().pop();
+        assert!(matches!(
+            PaymentsEngine::from_snapshot(snapshot),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+
+        let mut snapshot = original.snapshot();
+        snapshot.client_tx_index.insert(9, vec![1]);
+        assert!(matches!(
+            PaymentsEngine::from_snapshot(snapshot),
+            Err(EngineError::InvalidAccount { client: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_accounts_with_injected_transactions() {
+        let mut original = process_csv_str(RESTORE_INPUT);
+
+        let accounts = original
+            .accounts_iter()
+            .map(|a| {
+                let mut account = ClientAccount::new(a.client);
+                account.available = a.available;
+                account.held = a.held;
+                account.total = a.total;
+                account.locked = a.locked;
+                (a.client, account)
+            })
+            .collect();
+        let mut restored = PaymentsEngine::from_accounts(accounts).unwrap();
+        for tx in original.snapshot().transactions.into_values() {
+            restored.inject_stored_transaction(tx).unwrap();
+        }
+        // Compare written state: rebuilt accounts carry no event history.
+        let output = |engine: &PaymentsEngine| engine.write_output_to_string().unwrap();
+        assert_eq!(output(&original), output(&restored));
+        assert_eq!(
+            original.snapshot().transactions,
+            restored.snapshot().transactions
+        );
+
+        original
+            .process_csv_bytes(RESTORE_FOLLOW_UP.as_bytes())
+            .unwrap();
+        restored
+            .process_csv_bytes(RESTORE_FOLLOW_UP.as_bytes())
+            .unwrap();
+        assert_eq!(output(&original), output(&restored));
+    }
+
+    #[test]
+    fn test_from_accounts_rejects_invalid_state() {
+        let mut account = ClientAccount::new(1);
+        account.available = Decimal4::from_str("5.0").unwrap();
+        let result = PaymentsEngine::from_accounts(HashMap::from([(1, account)]));
+        assert!(matches!(
+            result,
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+
+        let result = PaymentsEngine::from_accounts(HashMap::from([(2, ClientAccount::new(1))]));
+        assert!(matches!(
+            result,
+            Err(EngineError::InvalidAccount { client: 2, .. })
+        ));
+
+        let mut account = ClientAccount::new(3);
+        account.available = Decimal4::new(rust_decimal::Decimal::MAX);
+        account.held = account.available;
+        let result = PaymentsEngine::from_accounts(HashMap::from([(3, account)]));
+        assert!(matches!(
+            result,
+            Err(EngineError::InvalidAccount { client: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_inject_stored_transaction_errors() {
+        let mut engine =
+            PaymentsEngine::from_accounts(HashMap::from([(1, ClientAccount::new(1))])).unwrap();
+        let amount = Decimal4::from_str("1.0").unwrap();
+
+        let err = engine
+            .inject_stored_transaction(StoredTransaction::from_deposit(1, 2, amount))
+            .unwrap_err();
+        assert!(matches!(err, EngineError::InvalidAccount { client: 2, .. }));
+
+        engine
+            .inject_stored_transaction(StoredTransaction::from_deposit(1, 1, amount))
+            .unwrap();
+        let err = engine
+            .inject_stored_transaction(StoredTransaction::from_deposit(1, 1, amount))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::DuplicateTxId { tx_id: 1, row: 0 }
+        ));
+    }
+
+    #[test]
+    fn test_negative_deposit_is_skipped() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,-50.0
+withdrawal,1,2,-5.0"#;
+        let engine = process_csv_str(csv);
+        assert!(engine.get_account(1).is_none());
+
+        let negative = Decimal4::from_str("-50.0").unwrap();
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_transactions_iter(
+                [
+                    ParsedTransaction {
+                        tx_id: 1,
+                        client: 1,
+                        kind: TxKind::Deposit(negative),
+                    },
+                    ParsedTransaction {
+                        tx_id: 2,
+                        client: 1,
+                        kind: TxKind::Withdrawal(negative),
+                    },
+                ]
+                .into_iter(),
+            )
+            .unwrap();
+        let account = engine.get_account(1).unwrap();
+        assert!(account.available.is_zero());
+        assert!(account.total.is_zero());
+        assert!(engine.transactions.is_empty());
+    }
+
+    #[test]
+    fn test_process_csv_strict_rejects_negative_amount() {
+        let csv = "type,client,tx,amount\ndeposit,1,7,-50.0\n";
+        let mut engine = PaymentsEngine::new();
+        match engine.process_csv_strict(Cursor::new(csv)) {
+            Err(EngineError::NegativeAmount { tx_id, amount }) => {
+                assert_eq!(tx_id, 7);
+                assert_eq!(amount.to_string(), "-50.0000");
+            }
+            other => panic!("Expected NegativeAmount, got {:?}", other),
+        }
+        assert!(engine.get_account(1).is_none());
+
+        let csv = "type,client,tx,amount\ndeposit,1,8,-0.00001\n";
+        match engine.process_csv_strict(Cursor::new(csv)) {
+            Err(EngineError::InvalidRecord { row: 2, message }) => {
+                assert!(message.contains("-0.00001"), "{message}");
+            }
+            other => panic!("Expected InvalidRecord, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_max_transactions_per_client() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,1.0
+deposit,1,2,2.0
+deposit,2,3,5.0
+deposit,1,4,4.0
+withdrawal,1,5,0.5"#;
+
+        let mut engine = PaymentsEngine::with_config(EngineConfig {
+            max_transactions_per_client: Some(2),
+            ..EngineConfig::default()
+        });
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+
+        let account = engine.get_account(1).unwrap();
+        assert_eq!(account.available, Decimal4::from_str("2.5").unwrap());
+        assert_eq!(account.total, Decimal4::from_str("2.5").unwrap());
+        assert!(!engine.transactions.contains_key(&4));
+        assert_eq!(
+            engine.get_account(2).unwrap().available,
+            Decimal4::from_str("5.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_drain_locked_accounts() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+deposit,2,3,1.0
+deposit,3,4,7.0
+dispute,2,2,
+chargeback,2,2,
+dispute,3,4,
+chargeback,3,4,"#;
+
+        let mut engine = process_csv_str(csv);
+        let drained = engine.drain_locked_accounts();
+        assert_eq!(
+            drained.iter().map(|a| a.client).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert!(drained.iter().all(|a| a.locked));
+        assert_eq!(engine.transactions.len(), 1);
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            "client,available,held,total,locked\n1,10.0000,0.0000,10.0000,false\n"
+        );
+        assert!(engine.drain_locked_accounts().is_empty());
+    }
+
+    #[test]
+    fn test_drain_account() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0"#;
+
+        let mut engine = process_csv_str(csv);
+        let account = engine.drain_account(1).unwrap();
+        assert_eq!(account.available, Decimal4::from_str("10.0").unwrap());
+        assert!(engine.drain_account(1).is_none());
+        assert!(!engine.transactions.contains_key(&1));
+        assert!(!engine.write_output_to_string().unwrap().contains("\n1,"));
+    }
+
+    #[test]
+    fn test_process_csv_files_shares_tx_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.csv");
+        let second = dir.path().join("second.csv");
+        std::fs::write(&first, "type,client,tx,amount\ndeposit,1,1,10.0\n").unwrap();
+        std::fs::write(
+            &second,
+            "type,client,tx,amount\ndeposit,2,1,99.0\ndeposit,2,2,5.0\n",
+        )
+        .unwrap();
+
+        let engine = PaymentsEngine::process_csv_files(&[&first, &second]).unwrap();
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            "client,available,held,total,locked\n\
+             1,10.0000,0.0000,10.0000,false\n\
+             2,5.0000,0.0000,5.0000,false\n"
+        );
+
+        let missing = dir.path().join("missing.csv");
+        assert!(PaymentsEngine::process_csv_files(&[first, missing]).is_err());
+    }
+
+    #[test]
+    fn test_transactions_for_client() {
+        let csv = r#"type,client,tx,amount
+deposit,1,9,1.0
+deposit,2,3,2.0
+deposit,1,4,3.0
+withdrawal,1,5,0.5
+dispute,1,9,"#;
+
+        let mut engine = process_csv_str(csv);
+        let txs = engine.transactions_for_client(1);
+        assert_eq!(txs.iter().map(|t| t.tx_id).collect::<Vec<_>>(), vec![4, 9]);
+        assert!(txs[1].under_dispute);
+        assert!(engine.transactions_for_client(3).is_empty());
+
+        engine.drain_account(1);
+        assert!(engine.transactions_for_client(1).is_empty());
+        assert_eq!(engine.transactions_for_client(2).len(), 1);
+    }
+
+    #[test]
+    fn test_process_csv_with_header_map() {
+        let map = HeaderMap {
+            tx_type: "transaction_type".to_string(),
+            client: "account_id".to_string(),
+            tx: "transaction_id".to_string(),
+            amount: "value".to_string(),
+        };
+        let mapped = r#"account_id,transaction_type,value,transaction_id
+1,deposit,10.0,1
+1,withdrawal,2.5,2
+1,dispute,,1"#;
+        let standard = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,2.5
+dispute,1,1,"#;
+
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_csv_with_header_map(mapped.as_bytes(), &map)
+            .unwrap();
+        assert!(engine == process_csv_str(standard));
+    }
+
+    #[test]
+    fn test_reject_zero_amounts() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,0.0\nwithdrawal,1,2,0\n";
+        let mut engine = PaymentsEngine::builder().reject_zero_amounts(true).build();
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert!(engine.transactions.is_empty());
+        assert_eq!(engine.report().total_withdrawals, 0);
+        assert_eq!(process_csv_str(csv).report().total_deposits, 1);
+    }
+
+    #[test]
+    fn test_strict_duplicate_tx_ids() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,2,1,5.0\n";
+        let mut engine = PaymentsEngine::builder()
+            .strict_duplicate_tx_ids(true)
+            .build();
+        let err = engine.process_csv_strict(csv.as_bytes()).unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::DuplicateTxId { tx_id: 1, row: 3 }
+        ));
+        assert!(engine.get_account(1).is_none());
+    }
+
+    #[test]
+    fn test_disallow_negative_available() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,8.0
+dispute,1,1,"#;
+        let mut engine = PaymentsEngine::builder()
+            .allow_negative_available(false)
+            .build();
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        let account = engine.get_account(1).unwrap();
+        assert_eq!(account.available, Decimal4::from_str("2.0").unwrap());
+        assert!(account.held.is_zero());
+
+        let account = process_csv_str(csv).get_account(1).cloned().unwrap();
+        assert!(account.available.is_negative());
+    }
+
+    #[test]
+    fn test_max_clients_and_transactions() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,1.0
+deposit,3,3,1.0
+withdrawal,3,4,1.0
+deposit,1,5,1.0
+deposit,2,6,1.0"#;
+        let mut engine = PaymentsEngine::builder()
+            .max_clients(2)
+            .max_transactions(3)
+            .build();
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert!(engine.get_account(3).is_none());
+        assert_eq!(engine.transactions.len(), 3);
+        assert!(engine.transactions.contains_key(&5));
+        assert!(!engine.transactions.contains_key(&6));
+    }
+
+    #[test]
+    fn test_estimate_memory_usage() {
+        assert_eq!(PaymentsEngine::new().estimate_memory_usage(), 0);
+
+        let engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,2,2,1.0\n");
+        let estimate = engine.estimate_memory_usage();
+        assert_eq!(estimate, PaymentsEngine::estimated_memory_for(2, 2));
+        assert!(
+            estimate
+                > 2 * (std::mem::size_of::<ClientAccount>()
+                    + std::mem::size_of::<StoredTransaction>())
+        );
+        assert!(
+            PaymentsEngine::estimated_memory_for(2000, 2000)
+                > PaymentsEngine::estimated_memory_for(1000, 2000)
+        );
+        assert_eq!(
+            PaymentsEngine::estimated_memory_for(0, usize::MAX / 8),
+            usize::MAX
+        );
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut engine = PaymentsEngine::with_capacity(100, 1000);
+        assert!(engine.accounts.capacity() >= 100);
+        assert!(engine.transactions.capacity() >= 1000);
+
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\ndeposit,1,1,1.0\n")
+            .unwrap();
+        assert_eq!(engine.accounts.len(), 1);
+    }
+
+    #[test]
+    fn test_process_csv_with_timeout() {
+        let mut csv = String::from("type,client,tx,amount\n");
+        for tx in 1..=2500 {
+            csv.push_str(&format!("deposit,1,{},1.0\n", tx));
+        }
+
+        let mut engine = PaymentsEngine::new();
+        match engine.process_csv_with_timeout(csv.as_bytes(), Duration::ZERO) {
+            Err(EngineError::Timeout { rows_processed }) => {
+                assert_eq!(rows_processed, 1000);
+                assert_eq!(engine.transactions.len(), 1000);
+                assert_eq!(
+                    engine.get_account(1).unwrap().total,
+                    Decimal4::from_str("1000").unwrap()
+                );
+            }
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+
+        let mut engine = PaymentsEngine::new();
+        let applied = engine
+            .process_csv_with_timeout(
+                "type,client,tx,amount\n\
+                 deposit,1,1,5.0\n\
+                 not,a,row\n\
+                 withdrawal,1,2,9.0\n\
+                 withdrawal,1,3,2.0\n"
+                    .as_bytes(),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        assert_eq!(applied, 2);
+
+        let mut engine = PaymentsEngine::new();
+        let rows = engine
+            .process_csv_with_timeout(csv.as_bytes(), Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(rows, 2500);
+    }
+
+    #[test]
+    fn test_write_output_extended() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,4.0
+deposit,2,3,3.0
+dispute,2,3,
+chargeback,2,3,"#;
+
+        let engine = process_csv_str(csv);
+        let mut output = Vec::new();
+        engine.write_output_extended(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "client,available,held,total,locked,total_deposited,total_withdrawn\n\
+             1,6.0000,0.0000,6.0000,false,10.0000,4.0000\n\
+             2,0.0000,0.0000,0.0000,true,3.0000,0.0000\n"
+        );
+        assert!(engine.accounts_iter().all(|a| a.check_extended_invariant()));
+    }
+
+    #[test]
+    fn test_write_output_with_bom() {
+        let engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,1.0\n");
+        let mut output = Vec::new();
+        engine.write_output_with_bom(&mut output).unwrap();
+        assert_eq!(&output[..3], &[0xEF, 0xBB, 0xBF]);
+        assert_eq!(
+            &output[3..],
+            engine.write_output_to_vec().unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn test_write_output_sorted_by() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,5.0
+deposit,2,2,50.0
+deposit,3,3,5.0
+deposit,4,4,20.0"#;
+        let engine = process_csv_str(csv);
+
+        let mut output = Vec::new();
+        engine
+            .write_output_sorted_by(&mut output, |a| std::cmp::Reverse(a.total))
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let clients: Vec<&str> = output
+            .lines()
+            .map(|l| l.split(',').next().unwrap())
+            .collect();
+        assert_eq!(clients, vec!["client", "2", "4", "1", "3"]);
+    }
+
+    #[test]
+    fn test_process_csv_resumable() {
+        let first = "type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,2,2,5.0\n";
+        let rest = "withdrawal,1,3,4.0\ndispute,2,2,\n";
+
+        let mut engine = PaymentsEngine::new();
+        let offset = engine.process_csv_resumable(Cursor::new(first), 0).unwrap();
+        assert_eq!(offset, first.len() as u64);
+        assert_eq!(engine.snapshot_with_offset(offset).offset, offset);
+
+        let full = format!("{}{}", first, rest);
+        let end = engine
+            .process_csv_resumable(Cursor::new(full.as_str()), offset)
+            .unwrap();
+        assert_eq!(end, full.len() as u64);
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            process_csv_str(&full).write_output_to_string().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_process_csv_resumable_skips_partial_line() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,2,2,5.0\n";
+        let mid_first_row = csv.find("deposit,1").unwrap() as u64 + 3;
+
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_csv_resumable(Cursor::new(csv), mid_first_row)
+            .unwrap();
+        assert!(engine.get_account(1).is_none());
+        assert!(engine.get_account(2).is_some());
+    }
+
+    #[test]
+    fn test_account_balance_histogram() {
+        let mut csv = String::from("type,client,tx,amount\n");
+        for client in 0..10 {
+            csv.push_str(&format!(
+                "deposit,{},{},{}\n",
+                client,
+                client,
+                client * 10 + 5
+            ));
+        }
+        let engine = process_csv_str(&csv);
+
+        let buckets: Vec<Decimal4> = [25u32, 50, 75].into_iter().map(Decimal4::from).collect();
+        assert_eq!(engine.account_balance_histogram(&buckets), vec![2, 3, 2, 3]);
+        assert_eq!(engine.account_balance_histogram(&[]), vec![10]);
+    }
+
+    #[test]
+    fn test_top_and_bottom_n_accounts() {
+        // Deterministic pseudo-random order of distinct amounts.
+        let mut csv = String::from("type,client,tx,amount\n");
+        let mut seed: u32 = 12345;
+        let mut amounts = Vec::new();
+        for client in 0..100u32 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let amount = (seed >> 8) % 100_000 * 100 + client;
+            amounts.push(amount);
+            csv.push_str(&format!("deposit,{},{},{}\n", client, client, amount));
+        }
+        let engine = process_csv_str(&csv);
+
+        let max = *amounts.iter().max().unwrap();
+        let top = engine.top_n_accounts_by_total(1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].total, Decimal4::from(max));
+
+        let top3 = engine.top_n_accounts_by_total(3);
+        assert!(top3.windows(2).all(|w| w[0].total >= w[1].total));
+
+        let min = *amounts.iter().min().unwrap();
+        let bottom = engine.bottom_n_accounts_by_available(2);
+        assert_eq!(bottom[0].available, Decimal4::from(min));
+        assert!(bottom[0].available <= bottom[1].available);
+
+        assert!(engine.top_n_accounts_by_total(0).is_empty());
+        assert_eq!(engine.top_n_accounts_by_total(500).len(), 100);
+    }
+
+    #[test]
+    fn test_strict_business_rules_insufficient_funds() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,5.0\nwithdrawal,1,2,7.5\n";
+        let mut engine = PaymentsEngine::builder()
+            .strict_business_rules(true)
+            .build();
+        match engine.process_csv_strict(csv.as_bytes()) {
+            Err(EngineError::InsufficientFunds {
+                client,
+                available,
+                requested,
+            }) => {
+                assert_eq!(client, 1);
+                assert_eq!(available, Decimal4::from_str("5.0").unwrap());
+                assert_eq!(requested, Decimal4::from_str("7.5").unwrap());
+            }
+            other => panic!("Expected InsufficientFunds, got {:?}", other),
+        }
+        assert!(engine.get_account(1).is_none());
+    }
+
+    #[test]
+    fn test_strict_business_rules_account_locked() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,5.0
+dispute,1,1,
+chargeback,1,1,
+deposit,1,2,1.0"#;
+        let mut engine = PaymentsEngine::builder()
+            .strict_business_rules(true)
+            .build();
+        let err = engine.process_csv_strict(csv.as_bytes()).unwrap_err();
+        assert!(matches!(err, EngineError::AccountLocked { client: 1 }));
+
+        // Lenient processing logs the error and keeps going.
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert!(engine.get_account(1).unwrap().locked);
+        assert!(PaymentsEngine::new()
+            .process_csv_strict(csv.as_bytes())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_strict_business_rules_client_mismatch() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,5.0
+deposit,2,2,1.0
+dispute,2,1,
+dispute,1,1,
+resolve,2,1,
+chargeback,2,1,"#;
+        let mut strict = PaymentsEngine::builder()
+            .strict_business_rules(true)
+            .build();
+        let err = strict.process_csv_strict(csv.as_bytes()).unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::TransactionClientMismatch {
+                tx_id: 1,
+                claimed_client: 2,
+                actual_client: 1,
+                operation: "dispute",
+            }
+        ));
+        assert!(strict.get_account(1).is_none());
+
+        for (operation, row) in [
+            ("resolve", "resolve,2,1,"),
+            ("chargeback", "chargeback,2,1,"),
+        ] {
+            let mut engine = PaymentsEngine::builder()
+                .strict_business_rules(true)
+                .build();
+            let input = format!("type,client,tx,amount\ndeposit,1,1,5.0\ndispute,1,1,\n{row}\n");
+            match engine.process_csv_strict(input.as_bytes()) {
+                Err(EngineError::TransactionClientMismatch { operation: op, .. }) => {
+                    assert_eq!(op, operation)
+                }
+                other => panic!("Expected TransactionClientMismatch, got {:?}", other.err()),
+            }
+        }
+
+        // Lenient mode ignores mismatched references.
+        let mut lenient = PaymentsEngine::new();
+        lenient.process_csv_strict(csv.as_bytes()).unwrap();
+        let account = lenient.get_account(1).unwrap();
+        assert_eq!(account.held, Decimal4::from_str("5.0").unwrap());
+        assert!(!account.locked);
+
+        // Lenient processing with strict rules logs the error and keeps going.
+        strict.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(strict.get_account(1).unwrap().held, account.held);
+    }
+
+    #[test]
+    fn test_partial_withdrawal_allowed() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,5.0
+withdrawal,1,2,7.5
+deposit,2,3,1.0
+dispute,2,3,
+withdrawal,2,4,1.0"#;
+        let mut engine = PaymentsEngine::builder()
+            .partial_withdrawal_allowed(true)
+            .build();
+        engine.process_csv_strict(csv.as_bytes()).unwrap();
+
+        let account = engine.get_account(1).unwrap();
+        assert_eq!(account.available, Decimal4::ZERO);
+        assert_eq!(account.total, Decimal4::ZERO);
+        assert_eq!(
+            account.total_withdrawn(),
+            Decimal4::from_str("5.0").unwrap()
+        );
+        let stats = engine.account_stats(1).unwrap();
+        assert_eq!(stats.withdrawal_count, 1);
+        assert_eq!(stats.total_withdrawn, Decimal4::from_str("5.0").unwrap());
+
+        // Nothing is available while the deposit is held.
+        assert_eq!(engine.account_stats(2).unwrap().withdrawal_count, 0);
+
+        let mut full_only = PaymentsEngine::new();
+        full_only.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(
+            full_only.get_account(1).unwrap().available,
+            Decimal4::from_str("5.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_partial_withdrawal_reports_shortfall() {
+        use std::sync::{Arc, Mutex};
+
+        struct ShortfallHooks(Arc<Mutex<Vec<(u32, Decimal4, Decimal4)>>>);
+
+        impl EngineHooks for ShortfallHooks {
+            fn on_partial_withdrawal(
+                &mut self,
+                tx: &ParsedTransaction,
+                _account: &ClientAccount,
+                shortfall: Decimal4,
+            ) {
+                let TxKind::Withdrawal(actual) = tx.kind else {
+                    panic!("not a withdrawal: {tx:?}");
+                };
+                self.0.lock().unwrap().push((tx.tx_id, actual, shortfall));
+            }
+        }
+
+        let shortfalls = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = PaymentsEngine::builder()
+            .partial_withdrawal_allowed(true)
+            .hooks(ShortfallHooks(Arc::clone(&shortfalls)))
+            .build();
+        engine
+            .process_csv_bytes(
+                b"type,client,tx,amount\ndeposit,1,1,5.0\nwithdrawal,1,2,1.0\nwithdrawal,1,3,7.5\n"
+                    .as_slice(),
+            )
+            .unwrap();
+
+        let d = |s: &str| Decimal4::from_str(s).unwrap();
+        assert_eq!(*shortfalls.lock().unwrap(), [(3, d("4.0"), d("3.5"))]);
+    }
+
+    #[test]
+    fn test_bulk_deposit() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,5.0
+deposit,3,2,1.0
+dispute,3,2,
+chargeback,3,2,"#;
+        let mut engine = process_csv_str(csv);
+        let bonus = Decimal4::from_str("2.5").unwrap();
+
+        assert_eq!(engine.bulk_deposit(&[1, 2, 3], bonus, 100), 2);
+        assert_eq!(
+            engine.get_account(1).unwrap().available,
+            Decimal4::from_str("7.5").unwrap()
+        );
+        assert_eq!(engine.get_account(2).unwrap().available, bonus);
+        assert!(engine.get_account(3).unwrap().available.is_zero());
+        assert!(!engine.transactions.contains_key(&102));
+
+        // Tx 101 is taken, so nothing from this batch is applied.
+        let before = engine.clone();
+        assert_eq!(engine.bulk_deposit(&[4, 5], bonus, 100), 0);
+        assert_eq!(engine.bulk_deposit(&[4, 5], bonus, 99), 0);
+        assert!(engine == before);
+
+        assert_eq!(engine.bulk_deposit(&[4, 5], bonus, u32::MAX), 0);
+        assert_eq!(engine.bulk_deposit(&[], bonus, 500), 0);
+    }
+
+    #[test]
+    fn test_remove_account() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+deposit,2,3,1.0
+dispute,2,2,
+dispute,2,3,"#;
+        let mut engine = process_csv_str(csv);
+
+        let removed = engine.remove_account(1).unwrap().unwrap();
+        assert_eq!(removed.client, 1);
+        assert!(engine.get_account(1).is_none());
+        assert!(!engine.transactions.contains_key(&1));
+        assert!(!engine.write_output_to_string().unwrap().contains("\n1,"));
+        assert!(engine.remove_account(1).unwrap().is_none());
+
+        let err = engine.remove_account(2).unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::ActiveDisputes {
+                client: 2,
+                count: 2
+            }
+        ));
+        assert!(engine.get_account(2).is_some());
+
+        let mut imported = PaymentsEngine::new();
+        imported
+            .import_csv_accounts(
+                b"client,available,held,total,locked
+3,1.0,2.0,3.0,false
+"
+                .as_slice(),
+            )
+            .unwrap();
+        assert!(matches!(
+            imported.remove_account(3),
+            Err(EngineError::ActiveDisputes {
+                client: 3,
+                count: 0
+            })
+        ));
+        assert!(imported.get_account(3).is_some());
+    }
+
+    #[test]
+    fn test_dry_run_collects_all_errors() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+transfer,1,2,5.0
+deposit,1,3,-1.0
+withdrawal,1,4,50.0
+deposit,x,5,1.0
+deposit,2,6,1.0"#;
+        let mut engine = PaymentsEngine::builder()
+            .strict_business_rules(true)
+            .build();
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\ndeposit,9,100,1.0\n")
+            .unwrap();
+        let before = engine.clone();
+
+        let errors = engine.dry_run(csv.as_bytes());
+        let rows: Vec<usize> = errors.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, vec![3, 4, 5, 6]);
+        assert!(matches!(
+            errors[0].1,
+            EngineError::InvalidRecord { row: 3, .. }
+        ));
+        assert!(matches!(
+            errors[1].1,
+            EngineError::NegativeAmount { tx_id: 3, .. }
+        ));
+        assert!(matches!(
+            errors[2].1,
+            EngineError::InsufficientFunds { client: 1, .. }
+        ));
+        assert!(matches!(
+            errors[3].1,
+            EngineError::CsvPositionError { line: 6, .. }
+        ));
+
+        assert!(engine == before);
+        assert!(engine.get_account(1).is_none());
+        assert!(PaymentsEngine::new()
+            .dry_run("type,client,tx,amount\ndeposit,1,1,1.0\n".as_bytes())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_write_output_to_string_matches_write_output() {
+        let engine = process_csv_str("type,client,tx,amount\ndeposit,2,1,1.5\ndeposit,1,2,3.0\n");
+        let mut output = Vec::new();
+        engine.write_output(&mut output).unwrap();
+
+        assert_eq!(engine.write_output_to_vec().unwrap(), output);
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            "client,available,held,total,locked\n\
+             1,3.0000,0.0000,3.0000,false\n\
+             2,1.5000,0.0000,1.5000,false\n"
+        );
+    }
+
+    #[test]
+    fn test_import_csv_accounts() {
+        let exported = process_csv_str(
+            "type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,2,2,5.0\n\
+             dispute,2,2,\nchargeback,2,2,\n",
+        )
+        .write_output_to_string()
+        .unwrap();
+
+        let mut engine = PaymentsEngine::new();
+        assert_eq!(engine.import_csv_accounts(exported.as_bytes()).unwrap(), 2);
+        assert_eq!(engine.write_output_to_string().unwrap(), exported);
+
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\nwithdrawal,1,3,4.0\ndeposit,2,4,1.0\n")
+            .unwrap();
+        assert_eq!(
+            engine.get_account(1).unwrap().available,
+            Decimal4::from_str("6.0").unwrap()
+        );
+        assert_eq!(engine.get_account(2).unwrap().total, Decimal4::ZERO);
+    }
+
+    #[test]
+    fn test_import_csv_accounts_errors() {
+        let mut engine = PaymentsEngine::new();
+        let mismatch =
+            "client,available,held,total,locked\n1,1.0,0.0,1.0,false\n2,1.0,1.0,3.0,false\n";
+        assert!(matches!(
+            engine.import_csv_accounts(mismatch.as_bytes()),
+            Err(EngineError::InvalidRecord { row: 3, .. })
+        ));
+        assert!(engine.get_account(1).is_none());
+
+        let duplicate =
+            "client,available,held,total,locked\n1,1.0,0.0,1.0,false\n1,2.0,0.0,2.0,false\n";
+        assert!(matches!(
+            engine.import_csv_accounts(duplicate.as_bytes()),
+            Err(EngineError::DuplicateClient { client: 1 })
+        ));
+
+        assert!(matches!(
+            engine.import_csv_accounts(
+                "client,available,held,total,locked\n1,abc,0,0,false\n".as_bytes()
+            ),
+            Err(EngineError::CsvPositionError { line: 2, .. })
+        ));
+
+        let max = Decimal4::MAX_REPRESENTABLE;
+        let overflowing =
+            format!("client,available,held,total,locked\n1,{max},{max},{max},false\n");
+        assert!(matches!(
+            engine.import_csv_accounts(overflowing.as_bytes()),
+            Err(EngineError::InvalidRecord { row: 2, .. })
+        ));
+
+        let huge = "79228162514264337593543950335";
+        let unrepresentable =
+            format!("client,available,held,total,locked\n1,{huge},{huge},{huge},false\n");
+        assert!(matches!(
+            engine.import_csv_accounts(unrepresentable.as_bytes()),
+            Err(EngineError::CsvPositionError { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_csv_with_row_limit() {
+        let csv =
+            "type,client,tx,amount\ndeposit,1,1,1.0\nbad,row\ndeposit,1,2,2.0\ndeposit,1,3,4.0\n";
+
+        let mut engine = PaymentsEngine::new();
+        assert_eq!(
+            engine
+                .process_csv_with_row_limit(csv.as_bytes(), 3)
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            engine.get_account(1).unwrap().total,
+            Decimal4::from_str("3.0").unwrap()
+        );
+
+        let mut engine = PaymentsEngine::new();
+        assert_eq!(
+            engine
+                .process_csv_with_row_limit(csv.as_bytes(), 10)
+                .unwrap(),
+            4
+        );
+        assert_eq!(
+            engine.get_account(1).unwrap().total,
+            Decimal4::from_str("7.0").unwrap()
+        );
+
+        let mut engine = PaymentsEngine::new();
+        assert_eq!(
+            engine
+                .process_csv_with_row_limit(csv.as_bytes(), 0)
+                .unwrap(),
+            0
+        );
+        assert!(engine.get_account(1).is_none());
+    }
+
+    #[test]
+    fn test_highly_disputed_transactions() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,5.0
+deposit,2,3,1.0
+dispute,1,1,
+resolve,1,1,
+dispute,1,1,
+dispute,1,1,
+dispute,2,3,
+dispute,1,2,"#;
+        let engine = process_csv_str(csv);
+
+        let ids = |threshold| {
+            engine
+                .highly_disputed_transactions(threshold)
+                .iter()
+                .map(|tx| tx.tx_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(2), vec![1]);
+        assert_eq!(ids(1), vec![1, 2, 3]);
+        assert_eq!(ids(0), vec![1, 2, 3]);
+        assert!(ids(3).is_empty());
+    }
+
+    #[test]
+    fn test_write_output_compact() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,2,2,5.0
+deposit,3,3,4.0
+withdrawal,3,4,4.0
+deposit,4,5,2.0
+dispute,4,5,
+resolve,4,5,
+withdrawal,4,6,2.0"#;
+        let engine = process_csv_str(csv);
+
+        let standard = engine.write_output_to_string().unwrap();
+        assert!(standard.contains("\n2,0.0000,0.0000,0.0000,false\n"));
+
+        let mut compact = Vec::new();
+        engine.write_output_compact(&mut compact).unwrap();
+        let compact = String::from_utf8(compact).unwrap();
+        assert_eq!(
+            compact,
+            "client,available,held,total,locked\n\
+             1,10.0000,0.0000,10.0000,false\n\
+             4,0.0000,0.0000,0.0000,false\n"
+        );
+
+        let mut restored = PaymentsEngine::new();
+        assert_eq!(restored.import_csv_accounts(compact.as_bytes()).unwrap(), 2);
+        assert_eq!(restored.write_output_to_string().unwrap(), compact);
+    }
+
+    #[test]
+    fn test_csv_error_carries_position() {
+        let csv = "type,client,tx,amount\n\
+                   deposit,1,1,1.0\n\
+                   deposit,1,2,1.0\n\
+                   deposit,1,3,1.0\n\
+                   deposit,1,four,1.0\n";
+        let mut engine = PaymentsEngine::new();
+        match engine.process_csv_strict(csv.as_bytes()) {
+            Err(EngineError::CsvPositionError {
+                line,
+                byte,
+                message,
+            }) => {
+                assert_eq!(line, 5);
+                assert_eq!(byte, 70);
+                assert!(message.contains("field 2"), "{message}");
+            }
+            other => panic!("Expected CsvPositionError, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_chargeback_metrics() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,2.5
+dispute,1,1,
+chargeback,1,1,
+deposit,2,3,4.25
+dispute,2,3,
+chargeback,2,3,
+deposit,3,4,7.0
+dispute,3,4,
+resolve,3,4,"#;
+        let engine = process_csv_str(csv);
+
+        assert_eq!(
+            engine.total_chargebacked(),
+            Decimal4::from_str("14.25").unwrap()
+        );
+        assert_eq!(engine.charged_back_client_count(), 2);
+        assert_eq!(
+            engine.get_account(1).unwrap().total_charged_back(),
+            Decimal4::from_str("10.0").unwrap()
+        );
+        assert_eq!(PaymentsEngine::new().total_chargebacked(), Decimal4::ZERO);
+    }
+
+    #[test]
+    fn test_reprocess() {
+        let csv = r#"type,client,tx,amount
+deposit,2,5,3.0
+deposit,1,1,10.0
+deposit,1,2,2.5
+dispute,1,2,"#;
+        let engine = process_csv_str(csv);
+        let replayed = engine.reprocess().unwrap();
+        assert_eq!(
+            replayed.write_output_to_string().unwrap(),
+            engine.write_output_to_string().unwrap()
+        );
+        assert_eq!(
+            stored_ignoring_rows(&replayed),
+            stored_ignoring_rows(&engine)
+        );
+
+        // Withdrawals are not stored, so they are lost on replay.
+        let engine =
+            process_csv_str("type,client,tx,amount\ndeposit,1,1,10.0\nwithdrawal,1,2,4.0\n");
+        let replayed = engine.reprocess().unwrap();
+        assert_eq!(
+            replayed.get_account(1).unwrap().total,
+            Decimal4::from_str("10.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_account_stats() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,5.0
+withdrawal,1,3,3.0
+withdrawal,1,4,100.0
+dispute,1,1,
+resolve,1,1,
+dispute,1,1,
+chargeback,1,1,
+withdrawal,2,5,1.0"#;
+        let engine = process_csv_str(csv);
+
+        let stats = engine.account_stats(1).unwrap();
+        assert_eq!(
+            stats,
+            AccountStats {
+                client: 1,
+                deposit_count: 2,
+                withdrawal_count: 1,
+                dispute_count: 2,
+                chargeback_count: 1,
+                total_deposited: Decimal4::from_str("15.0").unwrap(),
+                total_withdrawn: Decimal4::from_str("3.0").unwrap(),
+            }
+        );
+        assert_eq!(engine.account_stats(2), Some(AccountStats::new(2)));
+        assert_eq!(engine.account_stats(3), None);
+
+        let all = engine.all_account_stats();
+        assert_eq!(all.iter().map(|s| s.client).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(all[0], stats);
+    }
+
+    #[test]
+    fn test_verify_all_invariants() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+dispute,2,2,"#;
+        let engine = process_csv_str(csv);
+        assert!(engine.verify_all_invariants().is_ok());
+        assert!(engine.find_orphan_transactions().is_empty());
+        assert!(engine.find_unknown_held_funds().is_empty());
+
+        let mut orphaned = engine.clone();
+        orphaned.accounts.remove(&1);
+        let orphans = orphaned.find_orphan_transactions();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].tx_id, 1);
+        assert!(matches!(
+            orphaned.verify_all_invariants(),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+
+        let mut overflowing = engine.clone();
+        let account = overflowing.get_account_mut(1).unwrap();
+        account.available = Decimal4::new(rust_decimal::Decimal::MAX);
+        account.held = account.available;
+        assert!(matches!(
+            overflowing.verify_all_invariants(),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+
+        let mut account = ClientAccount::new(3);
+        account.held = Decimal4::from_str("2.0").unwrap();
+        account.total = account.held;
+        let restored = PaymentsEngine::from_accounts(HashMap::from([(3, account)])).unwrap();
+        let unknown = restored.find_unknown_held_funds();
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].client, 3);
+        assert!(matches!(
+            restored.verify_all_invariants(),
+            Err(EngineError::InvalidAccount { client: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_with_delimiter() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,10.0\nwithdrawal,1,2,2.5\ndispute,1,1,\n";
+        let expected = process_csv_str(csv).write_output_to_string().unwrap();
+
+        for delimiter in [b'|', b'\t'] {
+            let input = csv.replace(',', &char::from(delimiter).to_string());
+            let mut engine = PaymentsEngine::new();
+            engine
+                .process_with_delimiter(input.as_bytes(), delimiter)
+                .unwrap();
+            assert_eq!(engine.write_output_to_string().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_write_output_json() {
+        let engine = process_csv_str("type,client,tx,amount\ndeposit,2,1,1.5\ndeposit,1,2,3.0\n");
+
+        let mut compact = Vec::new();
+        engine.write_output_json(&mut compact).unwrap();
+        let compact = String::from_utf8(compact).unwrap();
+        assert_eq!(compact.lines().count(), 1);
+
+        let mut pretty = Vec::new();
+        engine.write_output_json_pretty(&mut pretty).unwrap();
+        let pretty = String::from_utf8(pretty).unwrap();
+        assert!(pretty.lines().count() > 2);
+
+        let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact, pretty);
+        assert_eq!(compact[0]["client"], 1);
+        assert_eq!(compact[0]["available"], "3.0000");
+        assert_eq!(compact[1]["client"], 2);
+        assert_eq!(compact[1]["locked"], false);
+    }
+
+    #[test]
+    fn test_process_csv_chunked() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+dispute,2,2,
+not,a,row
+withdrawal,1,3,4.0
+deposit,1,4,1.0
+resolve,2,2,
+deposit,3,5,2.0
+deposit,1,6,3.0"#;
+        let expected = process_csv_str(csv).write_output_to_string().unwrap();
+
+        let mut engine = PaymentsEngine::new();
+        let stats = engine.process_csv_chunked(csv.as_bytes(), 3).unwrap();
+        assert_eq!(engine.write_output_to_string().unwrap(), expected);
+        assert_eq!(
+            stats,
+            ProcessingStats {
+                rows_read: 9,
+                rows_skipped: 1,
+                transactions_flushed: 4,
+            }
+        );
+        // Only the disputed deposit survives the flush after the last row.
+        assert_eq!(engine.transactions.len(), 1);
+        assert_eq!(engine.transactions_for_client(2)[0].tx_id, 2);
+        assert!(engine.transactions_for_client(1).is_empty());
+        assert!(!engine.client_tx_index.contains_key(&3));
+
+        let mut unflushed = PaymentsEngine::new();
+        let stats = unflushed.process_csv_chunked(csv.as_bytes(), 0).unwrap();
+        assert_eq!(stats.transactions_flushed, 0);
+        assert_eq!(unflushed.transactions.len(), 5);
+
+        // Replaying flushed deposits does not credit them again.
+        let replay = "type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,3,5,2.0\n";
+        engine.process_csv_chunked(replay.as_bytes(), 3).unwrap();
+        assert_eq!(engine.write_output_to_string().unwrap(), expected);
+
+        let mut strict = PaymentsEngine::with_config(EngineConfig {
+            strict_duplicate_tx_ids: true,
+            ..EngineConfig::default()
+        });
+        strict.process_csv_chunked(csv.as_bytes(), 3).unwrap();
+        assert!(matches!(
+            strict.process_csv_strict(replay.as_bytes()),
+            Err(EngineError::DuplicateTxId { tx_id: 1, row: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_accounts_changed_since() {
+        let mut engine =
+            process_csv_str("type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,2,2,5.0\n");
+        let clock = engine.logical_clock();
+        assert_eq!(clock, 2);
+        assert!(engine.accounts_changed_since(clock).is_empty());
+
+        engine
+            .process_csv_bytes(
+                b"type,client,tx,amount\n\
+                  withdrawal,2,3,1.0\n\
+                  withdrawal,1,4,100.0\n\
+                  deposit,3,5,1.0\n\
+                  dispute,1,99,\n",
+            )
+            .unwrap();
+        let changed: Vec<u16> = engine
+            .accounts_changed_since(clock)
+            .iter()
+            .map(|a| a.client)
+            .collect();
+        assert_eq!(changed, vec![2, 3]);
+        assert_eq!(engine.logical_clock(), 4);
+        assert_eq!(engine.get_account(3).unwrap().last_modified_at, 4);
+        assert_eq!(engine.accounts_changed_since(0).len(), 3);
+    }
+
+    /// Parses CSV that follows RFC 4180 with every field quoted, panicking on
+    /// any deviation: bare fields, stray quotes or line endings other than CRLF.
+    fn parse_rfc4180_quoted(input: &str) -> Vec<Vec<String>> {
+        assert!(input.ends_with("\r\n"), "last record must end with CRLF");
+        input[..input.len() - 2]
+            .split("\r\n")
+            .map(|line| {
+                assert!(!line.contains('\n') && !line.contains('\r'), "{line:?}");
+                let mut fields = Vec::new();
+                let mut chars = line.chars().peekable();
+                loop {
+                    assert_eq!(chars.next(), Some('"'), "field must be quoted: {line:?}");
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => field.push(c),
+                            None => panic!("unterminated field: {line:?}"),
+                        }
+                    }
+                    fields.push(field);
+                    match chars.next() {
+                        Some(',') => continue,
+                        None => break,
+                        Some(c) => panic!("unexpected {c:?} after field: {line:?}"),
+                    }
+                }
+                fields
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_write_output_csv_rfc4180() {
+        let engine = process_csv_str(
+            "type,client,tx,amount\ndeposit,2,1,1.5\ndeposit,1,2,3.0\n\
+             dispute,1,2,\nchargeback,1,2,\n",
+        );
+        let mut output = Vec::new();
+        engine.write_output_csv_rfc4180(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let records = parse_rfc4180_quoted(&output);
+        let expected: Vec<Vec<String>> = engine
+            .write_output_to_string()
+            .unwrap()
+            .lines()
+            .map(|line| line.split(',').map(str::to_string).collect())
+            .collect();
+        assert_eq!(records, expected);
+        assert_eq!(records[1], ["1", "0.0000", "0.0000", "0.0000", "true"]);
+    }
+
+    #[test]
+    fn test_process_csv_with_schema_validation() {
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_csv_with_schema_validation(
+                " Type ,CLIENT,tx,Amount\ndeposit,1,1,2.0\n".as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(
+            engine.get_account(1).unwrap().total,
+            Decimal4::from_str("2.0").unwrap()
+        );
+
+        let mut engine = PaymentsEngine::new();
+        match engine.process_csv_with_schema_validation(
+            "kind,client,txid,amount\ndeposit,1,1,2.0\n".as_bytes(),
+        ) {
+            Err(EngineError::InvalidRecord { row, message }) => {
+                assert_eq!(row, 1);
+                assert_eq!(
+                    message,
+                    "missing required column(s) type, tx; found kind, client, txid, amount"
+                );
+            }
+            other => panic!("Expected InvalidRecord, got {:?}", other.err()),
+        }
+        assert!(engine.get_account(1).is_none());
+    }
+
+    #[test]
+    fn test_merge_accounts() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,2,4.0
+withdrawal,1,3,1.0
+dispute,1,2,
+deposit,2,4,5.0
+deposit,3,5,1.0
+dispute,3,5,
+chargeback,3,5,"#;
+        let mut engine = process_csv_str(csv);
+        let source_total = engine.get_account(1).unwrap().total;
+        let target_total = engine.get_account(2).unwrap().total;
+        let clock = engine.logical_clock();
+
+        engine.merge_accounts(1, 2).unwrap();
+        assert!(engine.get_account(1).is_none());
+        assert_eq!(engine.logical_clock(), clock + 1);
+        let changed: Vec<u16> = engine
+            .accounts_changed_since(clock)
+            .iter()
+            .map(|a| a.client)
+            .collect();
+        assert_eq!(changed, vec![2]);
+        let merged = engine.get_account(2).unwrap();
+        assert_eq!(merged.total, source_total + target_total);
+        assert_eq!(merged.available, Decimal4::from_str("14.0").unwrap());
+        assert_eq!(merged.held, Decimal4::from_str("4.0").unwrap());
+        assert_eq!(merged.held_transaction_ids(), &[2]);
+        assert!(merged.check_extended_invariant());
+        let tx_ids: Vec<u32> = engine
+            .transactions_for_client(2)
+            .iter()
+            .map(|tx| tx.tx_id)
+            .collect();
+        assert_eq!(tx_ids, vec![1, 2, 4]);
+        assert_eq!(engine.account_stats(2).unwrap().deposit_count, 3);
+        assert!(engine.verify_all_invariants().is_ok());
+        #[cfg(feature = "audit_log")]
+        {
+            let rows: Vec<usize> = engine
+                .transaction_timeline_for_client(2)
+                .iter()
+                .map(TransactionEvent::row_idx)
+                .collect();
+            assert_eq!(rows, vec![6, 2, 3, 4, 5]);
+        }
+
+        // The transferred dispute can be resolved on the target.
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\nresolve,2,2,\n")
+            .unwrap();
+        assert!(engine.get_account(2).unwrap().held.is_zero());
+
+        assert!(matches!(
+            engine.merge_accounts(2, 3),
+            Err(EngineError::AccountLocked { client: 3 })
+        ));
+        assert!(matches!(
+            engine.merge_accounts(2, 9),
+            Err(EngineError::InvalidAccount { client: 9, .. })
+        ));
+        assert!(matches!(
+            engine.merge_accounts(2, 2),
+            Err(EngineError::InvalidAccount { client: 2, .. })
+        ));
+        assert!(engine.get_account(2).is_some());
+    }
+
+    #[test]
+    fn test_checkpoint_rollback() {
+        let header = "type,client,tx,amount\n";
+        let first = "deposit,1,1,10.0\ndeposit,2,2,5.0\nwithdrawal,1,3,2.0\ndispute,2,2,\ndeposit,3,4,1.0\n";
+        let discarded =
+            "resolve,2,2,\ndeposit,1,5,7.0\nwithdrawal,3,6,1.0\ndeposit,4,7,3.0\ndispute,1,1,\n";
+        let alternative = "chargeback,2,2,\ndeposit,1,8,1.0\ndeposit,5,9,2.0\nwithdrawal,1,10,3.0\ndeposit,3,5,4.0\n";
+
+        let mut engine = process_csv_str(&format!("{header}{first}"));
+        let checkpoint = engine.checkpoint();
+        engine
+            .process_csv_bytes(format!("{header}{discarded}").as_bytes())
+            .unwrap();
+        engine.rollback(checkpoint);
+        engine
+            .process_csv_bytes(format!("{header}{alternative}").as_bytes())
+            .unwrap();
+
+        let expected = process_csv_str(&format!("{header}{first}{alternative}"));
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            expected.write_output_to_string().unwrap()
+        );
+        assert_eq!(
+            stored_ignoring_rows(&engine),
+            stored_ignoring_rows(&expected)
+        );
+        assert_eq!(engine.report(), expected.report());
+        assert_eq!(engine.logical_clock(), expected.logical_clock());
+    }
+
+    #[test]
+    fn test_resolve_all_disputes() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,5.0\n\
+             deposit,2,3,7.5\n\
+             dispute,1,1,\n\
+             dispute,1,2,\n\
+             dispute,2,3,\n\
+             resolve,2,3,\n\
+             dispute,2,3,\n",
+        );
+        assert_eq!(engine.pending_disputes().len(), 3);
+        let clock = engine.logical_clock();
+
+        assert_eq!(engine.resolve_all_disputes(), 3);
+        assert_eq!(engine.report().total_disputes_resolved, 4);
+
+        assert!(engine.pending_disputes().is_empty());
+        assert!(engine.accounts_iter().all(|a| a.held == Decimal4::ZERO));
+        assert!(engine
+            .accounts_iter()
+            .all(|a| a.held_transaction_ids().is_empty()));
+        assert_eq!(
+            engine.get_account(1).unwrap().available.to_string(),
+            "15.0000"
+        );
+        assert_eq!(
+            engine.get_account(2).unwrap().available.to_string(),
+            "7.5000"
+        );
+        assert_eq!(engine.logical_clock(), clock + 3);
+        assert_eq!(engine.accounts_changed_since(clock).len(), 2);
+        assert_eq!(engine.resolve_all_disputes(), 0);
+    }
+
+    #[test]
+    fn test_resolve_all_disputes_on_locked_account() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,5.0\n\
+             dispute,1,1,\n\
+             dispute,1,2,\n\
+             chargeback,1,1,\n",
+        );
+        assert_eq!(engine.pending_disputes().len(), 1);
+
+        assert_eq!(engine.resolve_all_disputes(), 1);
+
+        assert!(engine.pending_disputes().is_empty());
+        let account = engine.get_account(1).unwrap();
+        assert!(account.locked);
+        assert_eq!(account.held, Decimal4::ZERO);
+        assert_eq!(account.available.to_string(), "5.0000");
+        assert_eq!(engine.report().total_disputes_resolved, 1);
+        assert!(engine.run_integrity_check().passed);
+    }
+
+    #[test]
+    fn test_simulate_chargeback_impact() {
+        let csv = "type,client,tx,amount\n\
+                   deposit,1,1,10.0\n\
+                   deposit,1,2,5.0\n\
+                   deposit,1,3,2.5\n\
+                   withdrawal,1,4,1.0\n\
+                   dispute,1,3,\n\
+                   dispute,1,2,\n\
+                   deposit,2,5,4.0\n";
+        let engine = process_csv_str(csv);
+        let before = engine.write_output_to_string().unwrap();
+
+        let simulated = engine.simulate_chargeback_impact(1).unwrap();
+        assert_eq!(engine.write_output_to_string().unwrap(), before);
+
+        let actual = process_csv_str(&format!("{csv}chargeback,1,2,\nchargeback,1,3,\n"));
+        let account = actual.get_account(1).unwrap();
+        assert_eq!(
+            simulated,
+            SimulatedChargebackResult {
+                resulting_available: account.available,
+                resulting_held: account.held,
+                resulting_total: account.total,
+                would_be_locked: account.locked,
+            }
+        );
+        assert!(simulated.would_be_locked);
+        assert_eq!(simulated.resulting_total.to_string(), "11.5000");
+
+        let untouched = engine.simulate_chargeback_impact(2).unwrap();
+        assert!(!untouched.would_be_locked);
+        assert_eq!(untouched.resulting_total.to_string(), "4.0000");
+        assert!(engine.simulate_chargeback_impact(3).is_none());
+    }
+
+    #[test]
+    fn test_write_output_fixed_width() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,7,1,12.5\n\
+             deposit,42,2,3.25\n\
+             withdrawal,7,3,0.0001\n\
+             dispute,42,2,\n\
+             chargeback,42,2,\n",
+        );
+        let mut out = Vec::new();
+        engine.write_output_fixed_width(&mut out, 5, 10).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            "    7    12.4999     0.0000    12.4999 false\n   42     0.0000     0.0000     0.0000  true\n"
+        );
+
+        // A fixed-width reader slices each line at the same offsets.
+        let parse = |line: &str| {
+            let (client, rest) = line.split_at(5);
+            let amounts: Vec<Decimal4> = (0..3)
+                .map(|i| Decimal4::from_str(rest[1 + i * 11..11 + i * 11].trim()).unwrap())
+                .collect();
+            let locked: bool = rest[34..].trim().parse().unwrap();
+            (client.trim().parse::<u16>().unwrap(), amounts, locked)
+        };
+        for line in text.lines() {
+            assert_eq!(line.len(), 5 + 3 * 11 + 6);
+            let (client, amounts, locked) = parse(line);
+            let account = engine.get_account(client).unwrap();
+            assert_eq!(amounts, [account.available, account.held, account.total]);
+            assert_eq!(locked, account.locked);
+        }
+
+        assert!(matches!(
+            engine.write_output_fixed_width(Vec::new(), 5, 6),
+            Err(EngineError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            engine.write_output_fixed_width(Vec::new(), 1, 10),
+            Err(EngineError::InvalidRecord { row: 2, .. })
+        ));
+
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\ndeposit,7,4,123456.0\n".as_slice())
+            .unwrap();
+        let mut out = Vec::new();
+        let err = engine
+            .write_output_fixed_width(&mut out, 5, 10)
+            .unwrap_err();
+        assert!(matches!(err, EngineError::InvalidRecord { row: 1, .. }));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_partition_by_lock_status() {
+        let engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,2,2,5.0\n\
+             deposit,2,3,2.5\n\
+             dispute,2,2,\n\
+             chargeback,2,2,\n\
+             deposit,3,4,7.0\n\
+             dispute,3,4,\n\
+             deposit,4,5,1.0\n\
+             dispute,4,5,\n\
+             chargeback,4,5,\n",
+        );
+        let total_funds = engine.total_funds();
+        let stored = engine.snapshot().transactions.len();
+
+        let (active, locked) = engine.partition_by_lock_status();
+
+        assert_eq!(active.total_funds() + locked.total_funds(), total_funds);
+        let clients = |e: &PaymentsEngine| -> Vec<u16> {
+            e.sorted_accounts().iter().map(|a| a.client).collect()
+        };
+        assert_eq!(clients(&active), [1, 3]);
+        assert_eq!(clients(&locked), [2, 4]);
+        assert!(active.locked_accounts().is_empty());
+        assert!(locked.accounts_iter().all(|a| a.locked));
+
+        assert_eq!(
+            active.snapshot().transactions.len() + locked.snapshot().transactions.len(),
+            stored
+        );
+        assert_eq!(locked.transactions_for_client(2).len(), 2);
+        assert!(active.transactions_for_client(2).is_empty());
+        assert_eq!(active.pending_disputes().len(), 1);
+        assert_eq!(locked.account_stats(2).unwrap().chargeback_count, 1);
+        assert!(active.find_orphan_transactions().is_empty());
+        assert!(locked.find_orphan_transactions().is_empty());
+    }
+
+    #[test]
+    fn test_admin_revert_chargeback() {
+        let csv = "type,client,tx,amount\n\
+                   deposit,1,1,10.0\n\
+                   deposit,1,2,4.0\n\
+                   deposit,2,3,1.0\n";
+        let mut engine = process_csv_str(&format!("{csv}dispute,1,2,\nchargeback,1,2,\n"));
+        assert!(engine.get_account(1).unwrap().locked);
+
+        assert!(matches!(
+            engine.admin_revert_chargeback(1, 1),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+        assert!(matches!(
+            engine.admin_revert_chargeback(2, 2),
+            Err(EngineError::InvalidAccount { client: 2, .. })
+        ));
+        assert!(matches!(
+            engine.admin_revert_chargeback(9, 2),
+            Err(EngineError::InvalidAccount { client: 9, .. })
+        ));
+
+        engine.admin_revert_chargeback(1, 2).unwrap();
+        let disputed = process_csv_str(&format!("{csv}dispute,1,2,\n"));
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            disputed.write_output_to_string().unwrap()
+        );
+        assert_eq!(engine.pending_disputes().len(), 1);
+        assert_eq!(engine.get_account(1).unwrap().held_transaction_ids(), [2]);
+        assert!(engine.verify_all_invariants().is_ok());
+        #[cfg(feature = "audit_log")]
+        {
+            let four = Decimal4::from_str("4.0").unwrap();
+            assert_eq!(
+                engine.get_audit_log(1).unwrap().last(),
+                Some(&AccountEvent::ChargebackReverted {
+                    tx_id: 2,
+                    amount: four
+                })
+            );
+            assert_eq!(
+                engine.transaction_timeline_for_client(1).last(),
+                Some(&TransactionEvent::ChargebackReverted {
+                    tx_id: 2,
+                    amount: four,
+                    row_idx: 0
+                })
+            );
+        }
+
+        engine
+            .process_csv_bytes(b"type,client,tx,amount\nresolve,1,2,\n".as_slice())
+            .unwrap();
+        assert_eq!(
+            engine.get_account(1).unwrap().available.to_string(),
+            "14.0000"
+        );
+    }
+
+    #[test]
+    fn test_admin_revert_chargeback_rejects_tx_never_charged_back() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,100.0\n\
+             deposit,1,2,100.0\n\
+             dispute,1,1,\n\
+             chargeback,1,1,\n",
+        );
+        let before = engine.write_output_to_string().unwrap();
+
+        assert!(matches!(
+            engine.admin_revert_chargeback(1, 2),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+        assert_eq!(engine.write_output_to_string().unwrap(), before);
+        assert!(engine.pending_disputes().is_empty());
+
+        engine.admin_revert_chargeback(1, 1).unwrap();
+        assert!(matches!(
+            engine.admin_revert_chargeback(1, 1),
+            Err(EngineError::InvalidAccount { client: 1, .. })
+        ));
+    }
+
+    #[cfg(feature = "audit_log")]
+    #[test]
+    fn test_transaction_timeline_for_client() {
+        let engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,2,2,3.0\n\
+             withdrawal,1,3,2.5\n\
+             withdrawal,1,4,100.0\n\
+             dispute,1,1,\n\
+             resolve,1,1,\n\
+             dispute,1,1,\n\
+             chargeback,1,1,\n\
+             deposit,1,5,1.0\n",
+        );
+        let amount = |s: &str| Decimal4::from_str(s).unwrap();
+        let (ten, two_half) = (amount("10.0"), amount("2.5"));
+        assert_eq!(
+            engine.transaction_timeline_for_client(1),
+            [
+                TransactionEvent::Deposited {
+                    tx_id: 1,
+                    amount: ten,
+                    row_idx: 2
+                },
+                TransactionEvent::Withdrew {
+                    tx_id: 3,
+                    amount: two_half,
+                    row_idx: 4
+                },
+                TransactionEvent::Disputed {
+                    tx_id: 1,
+                    amount: ten,
+                    row_idx: 6
+                },
+                TransactionEvent::Resolved {
+                    tx_id: 1,
+                    amount: ten,
+                    row_idx: 7
+                },
+                TransactionEvent::Disputed {
+                    tx_id: 1,
+                    amount: ten,
+                    row_idx: 8
+                },
+                TransactionEvent::Chargebacked {
+                    tx_id: 1,
+                    amount: ten,
+                    row_idx: 9
+                },
+            ]
+        );
+        assert_eq!(engine.transaction_timeline_for_client(2).len(), 1);
+        assert!(engine.transaction_timeline_for_client(3).is_empty());
+        assert_eq!(engine.snapshot().transactions[&2].row_idx, 3);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_process_csv_concurrent_matches_sequential() {
+        let mut csv = String::from("type,client,tx,amount\n");
+        for client in 0..50u32 {
+            let base = client * 10;
+            csv.push_str(&format!(
+                "deposit,{client},{base},10.0\n\
+                 deposit,{client},{},2.5\n\
+                 withdrawal,{client},{},3.0\n\
+                 dispute,{client},{base},\n",
+                base + 1,
+                base + 2
+            ));
+            if client % 3 == 0 {
+                csv.push_str(&format!("chargeback,{client},{base},\n"));
+            } else if client % 3 == 1 {
+                csv.push_str(&format!("resolve,{client},{base},\n"));
+            }
+        }
+        // Cross-client references: a repeated deposit ID and a dispute of
+        // another client's transaction.
+        csv.push_str("deposit,60,10,99.0\ndispute,61,11,\ndeposit,61,1000,1.0\nbad,row\n");
+
+        let mut expected = PaymentsEngine::new();
+        expected
+            .process_csv_bytes(b"type,client,tx,amount\ndeposit,7,5000,1.0\n".as_slice())
+            .unwrap();
+        let mut engine = expected.clone();
+        expected.process_csv_bytes(csv.as_bytes()).unwrap();
+        engine.process_csv_concurrent(csv.as_bytes()).unwrap();
+
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            expected.write_output_to_string().unwrap()
+        );
+        assert_eq!(
+            engine.snapshot().transactions,
+            expected.snapshot().transactions
+        );
+        assert_eq!(engine.report(), expected.report());
+        assert_eq!(engine.logical_clock(), expected.logical_clock());
+        assert_eq!(engine.all_account_stats(), expected.all_account_stats());
+        #[cfg(feature = "audit_log")]
+        assert_eq!(
+            engine.transaction_timeline_for_client(1),
+            expected.transaction_timeline_for_client(1)
+        );
+        assert_eq!(engine.get_account(60).unwrap().total, Decimal4::ZERO);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_process_csv_concurrent_notifies_hooks_in_order() {
+        use std::sync::{Arc, Mutex};
+
+        struct RecordingHooks(Arc<Mutex<Vec<u32>>>);
+
+        impl EngineHooks for RecordingHooks {
+            fn on_deposit(&mut self, tx: &ParsedTransaction, _account: &ClientAccount) {
+                self.0.lock().unwrap().push(tx.tx_id);
+            }
+
+            fn on_withdrawal(&mut self, tx: &ParsedTransaction, _account: &ClientAccount) {
+                self.0.lock().unwrap().push(tx.tx_id);
+            }
+        }
+
+        let mut csv = String::from("type,client,tx,amount\n");
+        for tx in 1..=40u32 {
+            csv.push_str(&format!("deposit,{},{tx},1.0\n", tx % 7));
+        }
+        csv.push_str("withdrawal,3,41,1.0\n");
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = PaymentsEngine::with_hooks(RecordingHooks(Arc::clone(&seen)));
+        engine.process_csv_concurrent(csv.as_bytes()).unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), (1..=41).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_write_output_diff_from_snapshot() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,2,2,5.0\n\
+             deposit,3,3,1.0\n\
+             deposit,4,4,2.0\n",
+        );
+        let baseline = engine.snapshot();
+
+        let mut out = Vec::new();
+        engine
+            .write_output_diff_from_snapshot(&mut out, &baseline)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked\n"
+        );
+
+        engine
+            .process_csv_bytes(
+                b"type,client,tx,amount\n\
+                  withdrawal,1,5,2.0\n\
+                  dispute,3,3,\n\
+                  withdrawal,4,6,50.0\n\
+                  deposit,5,7,3.0\n"
+                    .as_slice(),
+            )
+            .unwrap();
+
+        let mut out = Vec::new();
+        engine
+            .write_output_diff_from_snapshot(&mut out, &baseline)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked\n\
+             1,8.0000,0.0000,8.0000,false\n\
+             3,0.0000,1.0000,1.0000,false\n\
+             5,3.0000,0.0000,3.0000,false\n"
+        );
+    }
+
+    #[test]
+    fn test_get_account_mut_bypasses_invariants() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,2,2,5.0\n",
+        );
+        assert!(engine.get_account_mut(3).is_none());
+
+        let account = engine.get_account_mut(2).unwrap();
+        account.available = Decimal4::from_str("4.0").unwrap();
+        account.total = Decimal4::from_str("4.0").unwrap();
+        assert!(engine.verify_all_invariants().is_ok());
+        assert_eq!(engine.get_account(2).unwrap().total.to_string(), "4.0000");
+
+        engine.get_account_mut(2).unwrap().held = Decimal4::ONE;
+        assert!(matches!(
+            engine.verify_all_invariants(),
+            Err(EngineError::InvalidAccount { client: 2, .. })
+        ));
+
+        engine.get_account_mut(2).unwrap().total = Decimal4::from_str("5.0").unwrap();
+        let err = engine.verify_all_invariants().unwrap_err();
+        assert!(err.to_string().contains("without a disputed transaction"));
+    }
+
+    #[test]
+    fn test_run_integrity_check_reports_overflow() {
+        let huge = Decimal4::new(rust_decimal::Decimal::MAX);
+        let mut engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,1.0\n");
+        for tx_id in [2, 3] {
+            engine
+                .inject_stored_transaction(StoredTransaction {
+                    under_dispute: true,
+                    ..StoredTransaction::from_deposit(tx_id, 1, huge)
+                })
+                .unwrap();
+        }
+        let account = engine.get_account_mut(1).unwrap();
+        account.available = huge;
+        account.held = huge;
+
+        let report = engine.run_integrity_check();
+        assert!(!report.passed);
+        assert!(report
+            .violations
+            .iter()
+            .any(|v| matches!(v, IntegrityViolation::HeldMismatch { client: 1, .. })));
+        assert!(report
+            .violations
+            .iter()
+            .any(|v| matches!(v, IntegrityViolation::BalanceMismatch { client: 1, .. })));
+    }
+
+    #[test]
+    fn test_run_integrity_check() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,2.0\n\
+             dispute,1,1,\n\
+             deposit,2,3,5.0\n\
+             deposit,3,4,1.0\n",
+        );
+        assert_eq!(
+            engine.run_integrity_check(),
+            IntegrityReport {
+                passed: true,
+                violations: Vec::new(),
+            }
+        );
+
+        engine.get_account_mut(1).unwrap().held = Decimal4::ONE;
+        engine.get_account_mut(2).unwrap().total = Decimal4::ONE;
+        engine.accounts.remove(&3);
+        engine.client_tx_index.get_mut(&2).unwrap().push(2);
+
+        let report = engine.run_integrity_check();
+        assert!(!report.passed);
+        let ten = Decimal4::from_str("10.0").unwrap();
+        let five = Decimal4::from_str("5.0").unwrap();
+        assert_eq!(
+            report.violations,
+            [
+                IntegrityViolation::OrphanTransaction {
+                    tx_id: 4,
+                    client: 3
+                },
+                IntegrityViolation::HeldMismatch {
+                    client: 1,
+                    held: Decimal4::ONE,
+                    disputed: ten,
+                },
+                IntegrityViolation::BalanceMismatch {
+                    client: 1,
+                    available: Decimal4::from_str("2.0").unwrap(),
+                    held: Decimal4::ONE,
+                    total: Decimal4::from_str("12.0").unwrap(),
+                },
+                IntegrityViolation::BalanceMismatch {
+                    client: 2,
+                    available: five,
+                    held: Decimal4::ZERO,
+                    total: Decimal4::ONE,
+                },
+                IntegrityViolation::DuplicateTxId {
+                    tx_id: 2,
+                    occurrences: 2
+                },
+                IntegrityViolation::LifetimeTotalsMismatch {
+                    opening_balances: Decimal4::ZERO,
+                    deposited: Decimal4::from_str("17.0").unwrap(),
+                    withdrawn: Decimal4::ZERO,
+                    charged_back: Decimal4::ZERO,
+                    total_funds: Decimal4::from_str("13.0").unwrap(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_transactions() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,2.0\n\
+             deposit,2,3,5.0\n",
+        );
+        assert_eq!(engine.deduplicate_transactions(), 0);
+
+        engine.client_tx_index.get_mut(&1).unwrap().push(1);
+        engine.client_tx_index.get_mut(&1).unwrap().push(3);
+        engine.client_tx_index.get_mut(&2).unwrap().insert(0, 2);
+        assert!(!engine.run_integrity_check().passed);
+
+        assert_eq!(engine.deduplicate_transactions(), 3);
+        assert!(engine.run_integrity_check().passed);
+        assert_eq!(engine.client_tx_index[&1], [1, 2]);
+        assert_eq!(engine.client_tx_index[&2], [3]);
+        assert_eq!(engine.transactions_for_client(1).len(), 2);
+    }
+
+    #[test]
+    fn test_write_output_selecting_clients() {
+        let engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,5,1,5.0\n\
+             deposit,4,2,4.0\n\
+             deposit,3,3,3.0\n\
+             deposit,2,4,2.0\n\
+             deposit,1,5,1.0\n",
+        );
+
+        let mut out = Vec::new();
+        engine
+            .write_output_selecting_clients(&mut out, &[5, 1, 3, 9])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked\n\
+             1,1.0000,0.0000,1.0000,false\n\
+             3,3.0000,0.0000,3.0000,false\n\
+             5,5.0000,0.0000,5.0000,false\n"
+        );
+
+        let mut out = Vec::new();
+        engine
+            .write_output_selecting_clients(&mut out, &[])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_stored_transaction() {
+        let original = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,4.0\n\
+             dispute,1,2,\n",
+        );
+
+        // Balances persisted without the hold, as if the dispute were resolved.
+        let mut account = ClientAccount::new(1);
+        account.deposit(Decimal4::from_str("14.0").unwrap());
+        let mut engine = PaymentsEngine::from_accounts(HashMap::from([(1, account)])).unwrap();
+
+        let disputed = StoredTransaction {
+            under_dispute: true,
+            ..StoredTransaction::from_deposit(2, 1, Decimal4::from_str("4.0").unwrap())
+        };
+        engine
+            .insert_stored_transaction(StoredTransaction::from_deposit(
+                1,
+                1,
+                Decimal4::from_str("10.0").unwrap(),
+            ))
+            .unwrap();
+        engine.insert_stored_transaction(disputed.clone()).unwrap();
+        assert_eq!(
+            engine.write_output_to_string().unwrap(),
+            original.write_output_to_string().unwrap()
+        );
+        assert!(engine.run_integrity_check().passed);
+
+        assert!(matches!(
+            engine.insert_stored_transaction(disputed.clone()),
+            Err(EngineError::DuplicateTxId { tx_id: 2, row: 0 })
+        ));
+        assert!(matches!(
+            engine.insert_stored_transaction(StoredTransaction::from_deposit(3, 9, Decimal4::ONE)),
+            Err(EngineError::InvalidRecord { row: 0, .. })
+        ));
+        assert_eq!(
+            engine.get_account(1).unwrap().held,
+            Decimal4::from_str("4.0").unwrap()
+        );
 
-    fn process_csv_str(csv: &str) -> PaymentsEngine {
-        let mut engine = PaymentsEngine::new();
-        engine.process_csv(Cursor::new(csv)).unwrap();
         engine
+            .process_csv_bytes(b"type,client,tx,amount\nchargeback,1,2,\n".as_slice())
+            .unwrap();
+        assert!(engine.get_account(1).unwrap().locked);
+
+        let mut account = ClientAccount::new(2);
+        account.deposit(Decimal4::ONE);
+        let accounts = HashMap::from([(2, account)]);
+        let too_large = StoredTransaction {
+            under_dispute: true,
+            ..StoredTransaction::from_deposit(5, 2, Decimal4::from_str("3.0").unwrap())
+        };
+        let mut strict = PaymentsEngine::from_accounts(accounts.clone()).unwrap();
+        strict.config.allow_negative_available = false;
+        assert!(matches!(
+            strict.insert_stored_transaction(too_large.clone()),
+            Err(EngineError::InvalidRecord { row: 0, .. })
+        ));
+        assert_eq!(strict.get_account(2).unwrap().available, Decimal4::ONE);
+        assert!(strict.transactions.is_empty());
+
+        let mut lenient = PaymentsEngine::from_accounts(accounts).unwrap();
+        lenient.insert_stored_transaction(too_large).unwrap();
+        assert_eq!(
+            lenient.get_account(2).unwrap().available,
+            Decimal4::from_str("-2.0").unwrap()
+        );
     }
 
     #[test]
-    fn test_simple_deposits() {
-        let csv = r#"type,client,tx,amount
-deposit,1,1,10.0
-deposit,1,2,5.0
-deposit,2,3,20.0"#;
+    fn test_process_csv_with_client_filter() {
+        let csv = "type,client,tx,amount\n\
+                   deposit,1,1,1.0\n\
+                   deposit,2,2,2.0\n\
+                   deposit,3,3,3.0\n\
+                   dispute,2,2,\n\
+                   deposit,4,4,4.0\n\
+                   withdrawal,5,5,1.0\n";
+        let full = process_csv_str(csv);
 
-        let engine = process_csv_str(csv);
+        let mut even = PaymentsEngine::new();
+        even.process_csv_with_client_filter(csv.as_bytes(), |c| c % 2 == 0)
+            .unwrap();
+        let clients = |e: &PaymentsEngine| -> Vec<u16> {
+            e.sorted_accounts().iter().map(|a| a.client).collect()
+        };
+        assert_eq!(clients(&even), [2, 4]);
+        assert_eq!(even.snapshot().transactions.len(), 2);
+        let (ours, theirs) = (even.get_account(2).unwrap(), full.get_account(2).unwrap());
+        assert!(!output_differs(ours, theirs));
 
-        let acc1 = engine.get_account(1).unwrap();
-        assert_eq!(acc1.available.to_string(), "15.0000");
-        assert_eq!(acc1.total.to_string(), "15.0000");
+        let mut low = PaymentsEngine::new();
+        low.process_csv_for_client_range(csv.as_bytes(), 1, 3)
+            .unwrap();
+        let mut high = PaymentsEngine::new();
+        high.process_csv_for_client_range(csv.as_bytes(), 4, u16::MAX)
+            .unwrap();
+        assert_eq!(clients(&low), [1, 2, 3]);
+        assert_eq!(clients(&high), [4, 5]);
 
-        let acc2 = engine.get_account(2).unwrap();
-        assert_eq!(acc2.available.to_string(), "20.0000");
+        low.merge_from(high).unwrap();
+        assert_eq!(
+            low.write_output_to_string().unwrap(),
+            full.write_output_to_string().unwrap()
+        );
     }
 
     #[test]
-    fn test_withdrawal() {
-        let csv = r#"type,client,tx,amount
-deposit,1,1,10.0
-withdrawal,1,2,3.0"#;
+    fn test_total_deposited_and_withdrawn_ever() {
+        let mut engine = process_csv_str(
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             deposit,1,2,5.0\n\
+             withdrawal,1,3,3.0\n\
+             withdrawal,1,4,100.0\n\
+             deposit,2,5,7.5\n\
+             dispute,2,5,\n\
+             chargeback,2,5,\n\
+             deposit,3,6,2.0\n\
+             withdrawal,3,7,2.0\n",
+        );
+        let d = |s: &str| Decimal4::from_str(s).unwrap();
+        assert_eq!(engine.total_deposited_ever(), d("24.5"));
+        assert_eq!(engine.total_withdrawn_ever(), d("5.0"));
+        assert_eq!(engine.total_chargebacked(), d("7.5"));
+        assert_eq!(engine.total_funds(), d("12.0"));
+        assert!(engine.run_integrity_check().passed);
 
-        let engine = process_csv_str(csv);
-        let acc = engine.get_account(1).unwrap();
-        assert_eq!(acc.available.to_string(), "7.0000");
-        assert_eq!(acc.total.to_string(), "7.0000");
+        engine.drain_account(1);
+        assert_eq!(engine.total_deposited_ever(), d("9.5"));
+        assert_eq!(engine.total_withdrawn_ever(), d("2.0"));
+        assert!(engine.run_integrity_check().passed);
+
+        engine.get_account_mut(3).unwrap().total = Decimal4::ONE;
+        engine.get_account_mut(3).unwrap().available = Decimal4::ONE;
+        let report = engine.run_integrity_check();
+        assert_eq!(
+            report.violations,
+            [IntegrityViolation::LifetimeTotalsMismatch {
+                opening_balances: Decimal4::ZERO,
+                deposited: d("9.5"),
+                withdrawn: d("2.0"),
+                charged_back: d("7.5"),
+                total_funds: Decimal4::ONE,
+            }]
+        );
+
+        engine.reset();
+        assert_eq!(engine.total_deposited_ever(), Decimal4::ZERO);
     }
 
     #[test]
-    fn test_withdrawal_insufficient_funds() {
-        let csv = r#"type,client,tx,amount
-deposit,1,1,10.0
-withdrawal,1,2,15.0"#;
+    fn test_lifetime_totals_saturate_instead_of_overflowing() {
+        let max = Decimal4::MAX_REPRESENTABLE;
+        let mut csv = String::from("type,client,tx,amount\n");
+        for tx in 1..=3u32 {
+            csv.push_str(&format!("deposit,{tx},{tx},{max}\n"));
+        }
+        let mut engine = PaymentsEngine::new();
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
 
-        let engine = process_csv_str(csv);
-        let acc = engine.get_account(1).unwrap();
-        assert_eq!(acc.available.to_string(), "10.0000");
+        assert_eq!(engine.get_account(3).unwrap().total, max);
+        assert_eq!(engine.total_deposited_ever(), max);
+        assert_eq!(engine.total_funds(), max);
+        assert_eq!(engine.report().total_funds, max);
     }
 
     #[test]
-    fn test_dispute_resolve() {
-        let csv = r#"type,client,tx,amount
-deposit,1,1,10.0
-dispute,1,1,
-resolve,1,1,"#;
+    fn test_run_integrity_check_after_import() {
+        let original = process_csv_str("type,client,tx,amount\ndeposit,1,1,100.0\n");
+        let exported = original.write_output_to_string().unwrap();
 
-        let engine = process_csv_str(csv);
-        let acc = engine.get_account(1).unwrap();
-        assert_eq!(acc.available.to_string(), "10.0000");
-        assert_eq!(acc.held.to_string(), "0.0000");
-        assert!(!acc.locked);
+        let mut imported = PaymentsEngine::new();
+        imported.import_csv_accounts(exported.as_bytes()).unwrap();
+        let report = imported.run_integrity_check();
+        assert!(report.passed, "{:?}", report.violations);
+
+        let restored = PaymentsEngine::from_accounts(imported.accounts.clone()).unwrap();
+        assert!(restored.run_integrity_check().passed);
+
+        imported
+            .process_csv_bytes(
+                b"type,client,tx,amount\ndeposit,1,2,5.0\nwithdrawal,1,3,20.0\n".as_slice(),
+            )
+            .unwrap();
+        assert!(imported.run_integrity_check().passed);
+
+        imported.get_account_mut(1).unwrap().available = Decimal4::ONE;
+        imported.get_account_mut(1).unwrap().total = Decimal4::ONE;
+        let d = |s: &str| Decimal4::from_str(s).unwrap();
+        assert_eq!(
+            imported.run_integrity_check().violations,
+            [IntegrityViolation::LifetimeTotalsMismatch {
+                opening_balances: d("100.0"),
+                deposited: d("5.0"),
+                withdrawn: d("20.0"),
+                charged_back: Decimal4::ZERO,
+                total_funds: Decimal4::ONE,
+            }]
+        );
+
+        imported.clear();
+        imported
+            .process_csv_bytes(b"type,client,tx,amount\ndeposit,1,1,5.0\n".as_slice())
+            .unwrap();
+        assert!(imported.run_integrity_check().passed);
     }
 
     #[test]
-    fn test_dispute_chargeback() {
-        let csv = r#"type,client,tx,amount
-deposit,1,1,10.0
-dispute,1,1,
-chargeback,1,1,"#;
+    fn test_amount_on_dispute_ignored_unless_strict() {
+        let csv = "type,client,tx,amount\n\
+                   deposit,1,1,100.0\n\
+                   dispute,1,1,100.0\n";
 
         let engine = process_csv_str(csv);
-        let acc = engine.get_account(1).unwrap();
-        assert_eq!(acc.available.to_string(), "0.0000");
-        assert_eq!(acc.held.to_string(), "0.0000");
-        assert_eq!(acc.total.to_string(), "0.0000");
-        assert!(acc.locked);
+        assert_eq!(engine.get_account(1).unwrap().held.to_string(), "100.0000");
+
+        let mut strict_rules = PaymentsEngine::with_config(EngineConfig {
+            strict_business_rules: true,
+            ..EngineConfig::default()
+        });
+        strict_rules.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(strict_rules.get_account(1).unwrap().held, Decimal4::ZERO);
+
+        let mut strict = PaymentsEngine::new();
+        assert!(matches!(
+            strict.process_csv_strict(csv.as_bytes()),
+            Err(EngineError::InvalidRecord { row: 3, .. })
+        ));
     }
 
     #[test]
@@ -543,12 +5690,588 @@ deposit,1,1,1.0
 deposit,2,2,2.0"#;
 
         let engine = process_csv_str(csv);
-        let mut output = Vec::new();
-        engine.write_output(&mut output).unwrap();
-
-        let output_str = String::from_utf8(output).unwrap();
+        let output_str = engine.write_output_to_string().unwrap();
         assert!(output_str.contains("client,available,held,total,locked"));
         assert!(output_str.contains("1,1.0000,0.0000,1.0000,false"));
         assert!(output_str.contains("2,2.0000,0.0000,2.0000,false"));
     }
+
+    #[test]
+    fn test_filtered_output() {
+        let csv = r#"type,client,tx,amount
+deposit,3,1,10.0
+withdrawal,3,2,8.0
+dispute,3,1,
+deposit,1,3,5.0
+deposit,2,4,7.0
+dispute,2,4,
+chargeback,2,4,"#;
+        let engine = process_csv_str(csv);
+        let header = "client,available,held,total,locked\n";
+
+        let mut output = Vec::new();
+        engine.write_locked_accounts(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}2,0.0000,0.0000,0.0000,true\n", header)
+        );
+
+        let mut output = Vec::new();
+        engine.write_negative_balance_accounts(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}3,-8.0000,10.0000,2.0000,false\n", header)
+        );
+
+        let mut output = Vec::new();
+        engine
+            .write_output_with_filter(&mut output, |a| a.client > 100)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), header);
+    }
+
+    #[test]
+    fn test_process_csv_with_progress() {
+        let mut csv = String::from("type,client,tx,amount\n");
+        for tx in 1..=1001 {
+            csv.push_str(&format!("deposit,{},{},1.0\n", tx % 10, tx));
+        }
+
+        let mut calls = Vec::new();
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_csv_with_progress(Cursor::new(&csv), |rows| calls.push(rows))
+            .unwrap();
+
+        assert_eq!(calls, vec![1000]);
+        assert!(engine == process_csv_str(&csv));
+        assert_eq!(engine.total_funds().to_string(), "1001.0000");
+    }
+
+    #[test]
+    fn test_process_csv_with_progress_custom_interval() {
+        let csv = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,2,1.0\ndeposit,1,3,1.0";
+
+        let mut calls = Vec::new();
+        let mut engine = PaymentsEngine::with_config(EngineConfig {
+            progress_interval: 2,
+            ..EngineConfig::default()
+        });
+        engine
+            .process_csv_with_progress(Cursor::new(csv), |rows| calls.push(rows))
+            .unwrap();
+        assert_eq!(calls, vec![2]);
+
+        let mut engine = PaymentsEngine::with_config(EngineConfig {
+            progress_interval: 0,
+            ..EngineConfig::default()
+        });
+        engine
+            .process_csv_with_progress(Cursor::new(csv), |_| panic!("progress disabled"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tsv_output_matches_csv() {
+        let csv = r#"type,client,tx,amount
+deposit,2,1,2.0
+deposit,1,2,1.5
+dispute,2,1,"#;
+
+        let engine = process_csv_str(csv);
+        let csv_output = engine.write_output_to_string().unwrap();
+        let mut tsv_output = Vec::new();
+        engine.write_output_tsv(&mut tsv_output).unwrap();
+
+        let tsv_str = String::from_utf8(tsv_output).unwrap();
+        assert!(tsv_str.starts_with("client\tavailable\theld\ttotal\tlocked\n"));
+        assert!(tsv_str.contains("2\t0.0000\t2.0000\t2.0000\tfalse"));
+        assert_eq!(tsv_str.replace('\t', ","), csv_output);
+    }
+
+    #[test]
+    fn test_account_review_queries() {
+        let csv = r#"type,client,tx,amount
+deposit,3,1,10.0
+withdrawal,3,2,8.0
+dispute,3,1,
+deposit,1,3,5.0
+dispute,1,3,
+deposit,2,4,7.0
+dispute,2,4,
+chargeback,2,4,
+deposit,4,5,1.0"#;
+
+        let engine = process_csv_str(csv);
+        let clients = |accounts: Vec<&ClientAccount>| -> Vec<u16> {
+            accounts.iter().map(|a| a.client).collect()
+        };
+
+        assert_eq!(clients(engine.accounts_with_negative_balance()), vec![3]);
+        assert_eq!(clients(engine.accounts_with_held_funds()), vec![1, 3]);
+        assert_eq!(clients(engine.locked_accounts()), vec![2]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_process_csv_async_matches_sync() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,4.0
+deposit,2,3,3.0
+dispute,2,3,"#;
+
+        let mut async_engine = PaymentsEngine::new();
+        async_engine
+            .process_csv_async(tokio::io::BufReader::new(Cursor::new(csv)))
+            .await
+            .unwrap();
+        let sync_engine = process_csv_str(csv);
+
+        let async_output = async_engine.write_output_to_vec().unwrap();
+        let sync_output = sync_engine.write_output_to_vec().unwrap();
+
+        assert_eq!(async_output, sync_output);
+        assert_eq!(
+            async_engine.get_account(2).unwrap().held.to_string(),
+            "3.0000"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_process_channel_matches_sync() {
+        let txs = vec![
+            ParsedTransaction {
+                tx_id: 1,
+                client: 1,
+                kind: TxKind::Deposit(Decimal4::from_str("10.0").unwrap()),
+            },
+            ParsedTransaction {
+                tx_id: 2,
+                client: 1,
+                kind: TxKind::Withdrawal(Decimal4::from_str("3.5").unwrap()),
+            },
+            ParsedTransaction {
+                tx_id: 1,
+                client: 1,
+                kind: TxKind::Dispute,
+            },
+            ParsedTransaction {
+                tx_id: 3,
+                client: 2,
+                kind: TxKind::Deposit(Decimal4::from_str("1.0").unwrap()),
+            },
+        ];
+
+        let (tx_sender, rx) = tokio::sync::mpsc::channel(2);
+        let producer_txs = txs.clone();
+        let producer = tokio::spawn(async move {
+            for tx in producer_txs {
+                tx_sender.send(tx).await.unwrap();
+            }
+        });
+        let channel_engine = PaymentsEngine::process_channel(rx).await.unwrap();
+        producer.await.unwrap();
+
+        let mut sync_engine = PaymentsEngine::new();
+        sync_engine
+            .process_transactions_iter(txs.into_iter())
+            .unwrap();
+
+        assert!(channel_engine == sync_engine);
+        assert_eq!(
+            channel_engine.get_account(1).unwrap().available.to_string(),
+            "-3.5000"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_process_csv_async_empty_input() {
+        let mut engine = PaymentsEngine::new();
+        engine
+            .process_csv_async(tokio::io::BufReader::new(Cursor::new("")))
+            .await
+            .unwrap();
+        assert!(engine.accounts.is_empty());
+    }
+
+    #[test]
+    fn test_report_counts_applied_transactions() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,1,1,10.0
+withdrawal,1,2,4.0
+withdrawal,1,3,100.0
+dispute,1,1,
+deposit,2,4,3.0
+dispute,2,4,
+resolve,2,4,
+deposit,3,5,2.5
+dispute,3,5,
+chargeback,3,5,
+deposit,3,6,1.0"#;
+
+        let engine = process_csv_str(csv);
+        let report = engine.report();
+
+        assert_eq!(
+            report,
+            EngineReport {
+                total_clients: 3,
+                locked_clients: 1,
+                total_deposits: 3,
+                total_withdrawals: 1,
+                total_disputes_resolved: 1,
+                total_chargebacks: 1,
+                total_funds: Decimal4::from_str("9.0").unwrap(),
+                total_held: Decimal4::from_str("10.0").unwrap(),
+                negative_balance_clients: 1,
+            }
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("Clients:            3 (1 locked, 1 negative balance)"));
+        assert!(text.contains("Total held:         10.0000"));
+    }
+
+    #[test]
+    fn test_diff_with_self_is_empty() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+dispute,2,2,
+chargeback,2,2,"#;
+
+        let engine = process_csv_str(csv);
+        assert!(engine.diff(&engine).is_empty());
+        assert_eq!(engine.diff(&engine).to_string(), "");
+
+        let empty = PaymentsEngine::new();
+        assert!(empty.diff(&empty).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_changed() {
+        let before = process_csv_str(
+            r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+deposit,3,3,1.0"#,
+        );
+        let after = process_csv_str(
+            r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+withdrawal,2,3,2.0
+deposit,4,4,7.0"#,
+        );
+
+        let diff = before.diff(&after);
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added.iter().map(|a| a.client).collect::<Vec<_>>(),
+            vec![4]
+        );
+        assert_eq!(
+            diff.removed.iter().map(|a| a.client).collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.client, 2);
+
+        assert_eq!(
+            diff.to_string(),
+            "- 3,1.0000,0.0000,1.0000,false\n\
+             + 4,7.0000,0.0000,7.0000,false\n\
+             - 2,5.0000,0.0000,5.0000,false\n\
+             + 2,3.0000,0.0000,3.0000,false\n"
+        );
+    }
+
+    #[test]
+    fn test_clear_allows_engine_reuse() {
+        let first_batch = r#"type,client,tx,amount
+deposit,1,1,10.0
+dispute,1,1,
+chargeback,1,1,"#;
+        let second_batch = r#"type,client,tx,amount
+deposit,1,1,3.0
+deposit,2,2,4.0"#;
+
+        let mut engine = process_csv_str(first_batch);
+        engine.clear();
+
+        assert_eq!(engine.accounts_iter().count(), 0);
+        assert_eq!(engine.total_funds(), Decimal4::ZERO);
+        assert_eq!(engine.report().total_chargebacks, 1);
+
+        engine.process_csv_bytes(second_batch.as_bytes()).unwrap();
+        let fresh = process_csv_str(second_batch);
+        assert!(engine.diff(&fresh).is_empty());
+        assert_eq!(engine.accounts_iter().count(), 2);
+        assert_eq!(engine.total_funds().to_string(), "7.0000");
+    }
+
+    #[test]
+    fn test_reset_zeros_counters() {
+        let mut engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,10.0");
+        engine.reset();
+
+        assert_eq!(engine.accounts_iter().count(), 0);
+        assert_eq!(engine.report(), PaymentsEngine::new().report());
+    }
+
+    #[test]
+    fn test_process_batch_matches_csv() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,2,3.0
+bogus,1,3,1.0
+deposit,2,4,
+dispute,1,1,"#;
+        let csv_engine = process_csv_str(csv);
+
+        let mut batch_engine = PaymentsEngine::new();
+        batch_engine
+            .process_batch(vec![
+                TransactionRecord::deposit(1, 1, "10.0"),
+                TransactionRecord::withdrawal(1, 2, "3.0"),
+                TransactionRecord {
+                    tx_type: "bogus".to_string(),
+                    ..TransactionRecord::deposit(1, 3, "1.0")
+                },
+                TransactionRecord {
+                    amount: None,
+                    ..TransactionRecord::deposit(2, 4, "")
+                },
+                TransactionRecord::dispute(1, 1),
+            ])
+            .unwrap();
+
+        assert!(csv_engine.diff(&batch_engine).is_empty());
+        assert_eq!(
+            batch_engine.get_account(1).unwrap().held.to_string(),
+            "10.0000"
+        );
+    }
+
+    #[test]
+    fn test_clone_is_independent_snapshot() {
+        let original = process_csv_str(
+            r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0"#,
+        );
+        let mut snapshot = original.clone();
+        assert!(original == snapshot);
+
+        snapshot
+            .process_csv_bytes("type,client,tx,amount\nwithdrawal,1,3,4.0\ndispute,2,2,".as_bytes())
+            .unwrap();
+
+        assert!(original != snapshot);
+        assert_eq!(
+            original.get_account(1).unwrap().available.to_string(),
+            "10.0000"
+        );
+        assert!(!original.transactions[&2].under_dispute);
+        assert_eq!(
+            snapshot.get_account(1).unwrap().available.to_string(),
+            "6.0000"
+        );
+    }
+
+    #[test]
+    fn test_process_transactions_iter_matches_csv() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+withdrawal,1,3,2.5
+dispute,2,2,
+chargeback,2,2,"#;
+        let csv_engine = process_csv_str(csv);
+
+        let dec = |s: &str| Decimal4::from_str(s).unwrap();
+        let txs = vec![
+            ParsedTransaction {
+                tx_id: 1,
+                client: 1,
+                kind: TxKind::Deposit(dec("10.0")),
+            },
+            ParsedTransaction {
+                tx_id: 2,
+                client: 2,
+                kind: TxKind::Deposit(dec("5.0")),
+            },
+            ParsedTransaction {
+                tx_id: 3,
+                client: 1,
+                kind: TxKind::Withdrawal(dec("2.5")),
+            },
+            ParsedTransaction {
+                tx_id: 2,
+                client: 2,
+                kind: TxKind::Dispute,
+            },
+            ParsedTransaction {
+                tx_id: 2,
+                client: 2,
+                kind: TxKind::Chargeback,
+            },
+        ];
+        let mut iter_engine = PaymentsEngine::new();
+        iter_engine
+            .process_transactions_iter(txs.into_iter())
+            .unwrap();
+
+        let csv_output = csv_engine.write_output_to_vec().unwrap();
+        let iter_output = iter_engine.write_output_to_vec().unwrap();
+
+        assert_eq!(csv_output, iter_output);
+        assert!(iter_engine.get_account(2).unwrap().locked);
+    }
+
+    #[test]
+    fn test_configured_rounding_mode() {
+        use crate::decimal::RoundingMode;
+
+        let csv = r#"type,client,tx,amount
+deposit,1,1,1.00005"#;
+
+        let default_engine = process_csv_str(csv);
+        assert_eq!(
+            default_engine.get_account(1).unwrap().available.to_string(),
+            "1.0001"
+        );
+
+        let mut half_even_engine = PaymentsEngine::with_config(EngineConfig {
+            rounding_mode: RoundingMode::HalfEven,
+            ..EngineConfig::default()
+        });
+        half_even_engine.process_csv_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(
+            half_even_engine
+                .get_account(1)
+                .unwrap()
+                .available
+                .to_string(),
+            "1.0000"
+        );
+    }
+
+    #[test]
+    fn test_on_chargeback_fires_once_per_chargeback() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountingHooks {
+            chargebacks: Arc<AtomicUsize>,
+            locks: Arc<AtomicUsize>,
+        }
+
+        impl EngineHooks for CountingHooks {
+            fn on_chargeback(&mut self, _tx: &ParsedTransaction, account: &ClientAccount) {
+                assert!(account.locked);
+                self.chargebacks.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_account_locked(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {
+                self.locks.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let chargebacks = Arc::new(AtomicUsize::new(0));
+        let locks = Arc::new(AtomicUsize::new(0));
+        let mut engine = PaymentsEngine::with_hooks(CountingHooks {
+            chargebacks: Arc::clone(&chargebacks),
+            locks: Arc::clone(&locks),
+        });
+
+        let csv = r#"type,client,tx,amount
+deposit,1,1,10.0
+deposit,2,2,5.0
+chargeback,1,1,
+dispute,1,1,
+chargeback,1,1,
+chargeback,1,1,
+dispute,2,2,
+chargeback,2,2,"#;
+        engine.process_csv_bytes(csv.as_bytes()).unwrap();
+
+        assert_eq!(chargebacks.load(Ordering::SeqCst), 2);
+        assert_eq!(locks.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_merge_from_matches_sequential_processing() {
+        let shard_a = r#"type,client,tx,amount
+deposit,1,1,10.0
+withdrawal,1,3,4.0
+dispute,1,1,
+"#;
+        let shard_b = r#"type,client,tx,amount
+deposit,2,2,20.0
+dispute,2,2,
+chargeback,2,2,
+withdrawal,3,4,1.0
+"#;
+        let combined = format!(
+            "{}{}",
+            shard_a,
+            shard_b.lines().skip(1).collect::<Vec<_>>().join("\n")
+        );
+        let sequential = process_csv_str(&combined);
+
+        let handles: Vec<_> = [shard_a, shard_b]
+            .into_iter()
+            .map(|shard| std::thread::spawn(move || process_csv_str(shard)))
+            .collect();
+        let mut engines = handles.into_iter().map(|h| h.join().unwrap());
+        let mut merged = engines.next().unwrap();
+        for engine in engines {
+            merged.merge_from(engine).unwrap();
+        }
+
+        let sequential_output = sequential.write_output_to_vec().unwrap();
+        let merged_output = merged.write_output_to_vec().unwrap();
+
+        assert_eq!(sequential_output, merged_output);
+        assert_eq!(merged.transactions.len(), sequential.transactions.len());
+    }
+
+    #[test]
+    fn test_merge_from_rejects_conflicts() {
+        let mut engine = process_csv_str("type,client,tx,amount\ndeposit,1,1,10.0");
+
+        let same_client = process_csv_str("type,client,tx,amount\ndeposit,1,2,5.0");
+        assert!(matches!(
+            engine.merge_from(same_client),
+            Err(EngineError::DuplicateClient { client: 1 })
+        ));
+
+        let same_tx = process_csv_str("type,client,tx,amount\ndeposit,2,1,5.0");
+        assert!(matches!(
+            engine.merge_from(same_tx),
+            Err(EngineError::DuplicateTxId { tx_id: 1, .. })
+        ));
+
+        assert!(engine.get_account(2).is_none());
+        assert_eq!(engine.transactions[&1].client, 1);
+    }
+
+    #[test]
+    fn test_deposit_overflow_is_skipped() {
+        let csv = r#"type,client,tx,amount
+deposit,1,1,7922816251426433759354395.0335
+deposit,1,2,1.0
+dispute,1,2,"#;
+
+        let engine = process_csv_str(csv);
+        let acc = engine.get_account(1).unwrap();
+        assert_eq!(acc.available, Decimal4::MAX_REPRESENTABLE);
+        assert_eq!(acc.held, Decimal4::ZERO);
+        assert!(!engine.transactions.contains_key(&2));
+    }
 }
@@ -1,5 +1,6 @@
 //! Error types for the payments engine.
 
+use crate::decimal::Decimal4;
 use thiserror::Error;
 
 /// Result type alias for engine operations
@@ -9,22 +10,135 @@ pub type Result<T> = std::result::Result<T, EngineError>;
 #[derive(Error, Debug)]
 pub enum EngineError {
     /// Failed to open or read the input file
-    #[error("I/O error: {0}")]
+    #[error("I/O error")]
     Io(#[from] std::io::Error),
 
-    /// CSV parsing error
-    #[error("CSV parsing error: {0}")]
-    Csv(#[from] csv::Error),
+    /// CSV error without position information, e.g. when writing output
+    #[error("CSV parsing error")]
+    Csv(#[source] csv::Error),
+
+    /// CSV parsing error at a known input position
+    #[error("CSV parsing error at line {line} (byte {byte}): {message}")]
+    CsvPositionError {
+        line: u64,
+        byte: u64,
+        message: String,
+    },
+
+    /// JSON serialization error
+    #[error("JSON serialization error")]
+    Json(#[from] serde_json::Error),
 
     /// Invalid transaction record
     #[error("Invalid transaction at row {row}: {message}")]
     InvalidRecord { row: usize, message: String },
 
     /// Duplicate transaction ID
-    #[error("Duplicate transaction ID {tx_id} at row {row}")]
+    #[error(
+        "Duplicate transaction ID {tx_id} at row {row}; use PaymentsEngine::dry_run to list every duplicate in the input"
+    )]
     DuplicateTxId { tx_id: u32, row: usize },
 
+    /// Client account present in both engines being merged
+    #[error("Duplicate account for client {client}")]
+    DuplicateClient { client: u16 },
+
+    /// Deposit or withdrawal with an amount below zero
+    #[error("Negative amount {amount} for transaction {tx_id}")]
+    NegativeAmount { tx_id: u32, amount: Decimal4 },
+
+    /// Restored account state is inconsistent
+    #[error("Invalid account for client {client}: {message}")]
+    InvalidAccount { client: u16, message: String },
+
+    /// Applying a transaction would overflow an account balance
+    #[error("Arithmetic overflow applying transaction {tx_id} at row {row}")]
+    ArithmeticOverflow { tx_id: u32, row: usize },
+
+    /// Transaction for a frozen account (strict business rules only)
+    #[error("Account for client {client} is locked")]
+    AccountLocked { client: u16 },
+
+    /// Withdrawal exceeds the available balance (strict business rules only)
+    #[error(
+        "Insufficient funds for client {client}: available {available}, requested {requested}"
+    )]
+    InsufficientFunds {
+        client: u16,
+        available: Decimal4,
+        requested: Decimal4,
+    },
+
+    /// Dispute, resolve or chargeback referencing another client's
+    /// transaction (strict business rules only)
+    #[error(
+        "{operation} by client {claimed_client} references transaction {tx_id} of client {actual_client}"
+    )]
+    TransactionClientMismatch {
+        tx_id: u32,
+        claimed_client: u16,
+        actual_client: u16,
+        operation: &'static str,
+    },
+
+    /// Account cannot be removed while it holds funds for disputes
+    #[error("Client {client} has {count} active disputes")]
+    ActiveDisputes { client: u16, count: usize },
+
+    /// Processing deadline exceeded after `rows_processed` rows were applied
+    #[error("Timed out after processing {rows_processed} rows")]
+    Timeout { rows_processed: usize },
+
     /// Missing input file argument
-    #[error("Missing input file argument. Usage: payments-engine <input.csv>")]
+    #[error(
+        "Missing input file argument. Usage: payments-engine [--bom] [--delimiter <DELIM>] [--format <csv|json|json-pretty|ndjson>] <input.csv>... (DELIM is ',', '|' or 'tab')"
+    )]
     MissingArgument,
+
+    /// Command-line option with an unsupported value
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl From<csv::Error> for EngineError {
+    /// Keeps the line and byte offset of parse errors, falling back to
+    /// [`EngineError::Csv`] when the error has no position.
+    fn from(e: csv::Error) -> Self {
+        let Some(position) = e.position() else {
+            return EngineError::Csv(e);
+        };
+        let message = match e.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+            _ => e.to_string(),
+        };
+        EngineError::CsvPositionError {
+            line: position.line(),
+            byte: position.byte(),
+            message,
+        }
+    }
+}
+
+/// Reasons a raw transaction record fails validation.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TransactionValidationError {
+    /// Transaction type is not one of the supported kinds
+    #[error("Unknown transaction type '{0}' (column 'type')")]
+    UnknownType(String),
+
+    /// Deposit or withdrawal without an amount
+    #[error("Missing amount for {tx_type} (column 'amount')")]
+    MissingAmount { tx_type: String },
+
+    /// Amount is not a valid decimal number
+    #[error("Invalid amount '{0}' (column 'amount')")]
+    InvalidAmount(String),
+
+    /// Amount is below zero, holding the amount as written in the input
+    #[error("Amount {0} must not be negative (column 'amount')")]
+    NegativeAmount(String),
+
+    /// Dispute, resolve or chargeback carries an amount
+    #[error("Amount is not allowed for dispute, resolve or chargeback (column 'amount')")]
+    AmountPresentForNonMonetary,
 }
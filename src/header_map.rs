@@ -0,0 +1,93 @@
+//! Mapping of non-standard CSV column names to the expected ones.
+
+/// Column names used by an input CSV for each expected field.
+///
+/// Columns not named here are passed through unchanged, so the default map
+/// accepts standard input.
+///
+/// # Examples
+///
+/// ```
+/// use payments_engine::{HeaderMap, PaymentsEngine};
+///
+/// let map = HeaderMap {
+///     tx_type: "transaction_type".to_string(),
+///     client: "account_id".to_string(),
+///     tx: "transaction_id".to_string(),
+///     amount: "value".to_string(),
+/// };
+/// let csv = "transaction_type,account_id,transaction_id,value\ndeposit,1,1,5.0\n";
+///
+/// let mut engine = PaymentsEngine::new();
+/// engine.process_csv_with_header_map(csv.as_bytes(), &map).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderMap {
+    /// Column holding the transaction type (canonical name `type`).
+    pub tx_type: String,
+
+    /// Column holding the client ID (canonical name `client`).
+    pub client: String,
+
+    /// Column holding the transaction ID (canonical name `tx`).
+    pub tx: String,
+
+    /// Column holding the amount (canonical name `amount`).
+    pub amount: String,
+}
+
+impl HeaderMap {
+    /// Returns the canonical name for an input column.
+    pub(crate) fn canonical<'a>(&self, column: &'a str) -> &'a str {
+        if column == self.tx_type {
+            "type"
+        } else if column == self.client {
+            "client"
+        } else if column == self.tx {
+            "tx"
+        } else if column == self.amount {
+            "amount"
+        } else {
+            column
+        }
+    }
+}
+
+impl Default for HeaderMap {
+    fn default() -> Self {
+        HeaderMap {
+            tx_type: "type".to_string(),
+            client: "client".to_string(),
+            tx: "tx".to_string(),
+            amount: "amount".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_names() {
+        let map = HeaderMap {
+            tx_type: "transaction_type".to_string(),
+            client: "account_id".to_string(),
+            tx: "transaction_id".to_string(),
+            amount: "value".to_string(),
+        };
+        assert_eq!(map.canonical("transaction_type"), "type");
+        assert_eq!(map.canonical("account_id"), "client");
+        assert_eq!(map.canonical("transaction_id"), "tx");
+        assert_eq!(map.canonical("value"), "amount");
+        assert_eq!(map.canonical("memo"), "memo");
+    }
+
+    #[test]
+    fn test_default_is_identity() {
+        let map = HeaderMap::default();
+        for column in ["type", "client", "tx", "amount"] {
+            assert_eq!(map.canonical(column), column);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Event hooks for reacting to account changes.
+//!
+//! Hooks let an embedding service trigger side effects (metrics, messages)
+//! when the engine applies a transaction, without changing the core logic.
+
+use crate::account::ClientAccount;
+use crate::decimal::Decimal4;
+use crate::transaction::ParsedTransaction;
+
+/// Callbacks invoked by [`PaymentsEngine`](crate::PaymentsEngine) after a
+/// transaction has been applied.
+///
+/// Each method receives the transaction and the account state after the
+/// change. Ignored transactions (locked accounts, unknown references,
+/// insufficient funds, etc.) do not trigger hooks. All methods default to
+/// doing nothing, so implementors only override the events they need.
+pub trait EngineHooks: Send {
+    /// Called after a deposit is credited.
+    fn on_deposit(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Called after a withdrawal is debited.
+    fn on_withdrawal(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Called after `on_withdrawal` when a withdrawal was capped at the
+    /// available balance (see
+    /// [`EngineConfig::partial_withdrawal_allowed`](crate::EngineConfig::partial_withdrawal_allowed)).
+    /// `tx` carries the amount actually withdrawn; `shortfall` is the part of
+    /// the requested amount that was not.
+    fn on_partial_withdrawal(
+        &mut self,
+        _tx: &ParsedTransaction,
+        _account: &ClientAccount,
+        _shortfall: Decimal4,
+    ) {
+    }
+
+    /// Called after disputed funds are moved to held.
+    fn on_dispute(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Called after a dispute is resolved and funds are released.
+    fn on_resolve(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Called after a chargeback removes held funds.
+    fn on_chargeback(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Called when an account becomes locked, after `on_chargeback`.
+    fn on_account_locked(&mut self, _tx: &ParsedTransaction, _account: &ClientAccount) {}
+
+    /// Returns `true` if every callback does nothing, so the engine may apply
+    /// transactions in ways that skip notifications. Defaults to `false`.
+    fn is_noop(&self) -> bool {
+        false
+    }
+}
+
+/// Hooks implementation that ignores all events. Used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopHooks;
+
+impl EngineHooks for NoopHooks {
+    fn is_noop(&self) -> bool {
+        true
+    }
+}
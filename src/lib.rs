@@ -23,13 +23,35 @@
 //! ```
 
 pub mod account;
+pub mod builder;
+pub mod config;
 pub mod decimal;
+pub mod diff;
 pub mod engine;
 pub mod error;
+pub mod header_map;
+pub mod hooks;
+pub mod report;
+pub mod snapshot;
+pub mod stats;
+pub mod timeline;
 pub mod transaction;
 
-pub use account::ClientAccount;
-pub use decimal::Decimal4;
-pub use engine::PaymentsEngine;
-pub use error::{EngineError, Result};
-pub use transaction::{ParsedTransaction, StoredTransaction, TransactionRecord, TxKind};
+pub use account::{AccountEvent, ClientAccount};
+pub use builder::PaymentsEngineBuilder;
+pub use config::EngineConfig;
+pub use decimal::{Decimal4, RoundingMode};
+pub use diff::EngineDiff;
+pub use engine::{EngineCheckpoint, PaymentsEngine};
+pub use error::{EngineError, Result, TransactionValidationError};
+pub use header_map::HeaderMap;
+pub use hooks::{EngineHooks, NoopHooks};
+pub use report::{
+    EngineReport, IntegrityReport, IntegrityViolation, ProcessingStats, SimulatedChargebackResult,
+};
+pub use snapshot::EngineSnapshot;
+pub use stats::AccountStats;
+pub use timeline::TransactionEvent;
+pub use transaction::{
+    normalize_tx_type, ParsedTransaction, StoredTransaction, TransactionRecord, TxKind,
+};
@@ -7,11 +7,30 @@
 //!
 //! ```bash
 //! cargo run -- transactions.csv > accounts.csv
+//! cargo run -- shard1.csv shard2.csv > accounts.csv
 //! ```
 //!
+//! Multiple input files are processed in order with one shared transaction ID
+//! namespace; a transaction ID repeated in a later file is ignored.
+//!
+//! # Options
+//!
+//! - `--bom`: Prefix CSV output with a UTF-8 byte order mark for Excel;
+//!   rejected with any other `--format`
+//! - `--format <csv|json|json-pretty|ndjson>`: Output format (default `csv`)
+//! - `--delimiter <DELIM>`: Field separator of the input files: `,` (default),
+//!   `|` or `tab`
+//!
 //! # Environment Variables
 //!
 //! - `RUST_LOG`: Set to `debug` or `warn` to control logging verbosity
+//!
+//! With the `tracing` feature enabled, logs are emitted as structured
+//! `tracing` events (with `tx_id`, `client_id` and `tx_type` fields)
+//! instead of through `env_logger`.
+//!
+//! With the `progress` feature enabled, a row counter is shown on stderr
+//! while the input is processed.
 
 use payments_engine::{EngineError, PaymentsEngine, Result};
 use std::env;
@@ -20,30 +39,136 @@ use std::io::{self, BufReader};
 use std::process;
 
 fn main() {
-    env_logger::init();
+    init_logging();
 
     if let Err(e) = run() {
         eprintln!("Error: {}", e);
+        print_causes(&e);
         process::exit(1);
     }
 }
 
+/// Prints the chain of underlying errors of `e`, outermost first.
+fn print_causes(e: &EngineError) {
+    let mut source = std::error::Error::source(e);
+    while let Some(cause) = source {
+        eprintln!("  caused by: {}", cause);
+        source = cause.source();
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn init_logging() {
+    env_logger::init();
+}
+
+#[cfg(feature = "tracing")]
+fn init_logging() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(io::stderr)
+        .init();
+}
+
 fn run() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
+    let mut bom = false;
+    let mut delimiter = b',';
+    let mut format = OutputFormat::Csv;
+    let mut input_paths = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bom" => bom = true,
+            "--delimiter" => delimiter = parse_delimiter(args.next().as_deref())?,
+            "--format" => format = parse_format(args.next().as_deref())?,
+            _ => input_paths.push(arg),
+        }
+    }
+    if input_paths.is_empty() {
         return Err(EngineError::MissingArgument);
     }
-
-    let input_path = &args[1];
-    let file = File::open(input_path)?;
-    let reader = BufReader::new(file);
+    if bom && !matches!(format, OutputFormat::Csv) {
+        return Err(EngineError::InvalidArgument(
+            "--bom is only supported with --format csv".to_string(),
+        ));
+    }
 
     let mut engine = PaymentsEngine::new();
-    engine.process_csv(reader)?;
+    for input_path in &input_paths {
+        let file = File::open(input_path)?;
+        process_input(&mut engine, BufReader::new(file), delimiter)?;
+    }
 
     let stdout = io::stdout();
     let handle = stdout.lock();
-    engine.write_output(handle)?;
+    match format {
+        OutputFormat::Csv if bom => engine.write_output_with_bom(handle),
+        OutputFormat::Csv => engine.write_output(handle),
+        OutputFormat::Json => engine.write_output_json(handle),
+        OutputFormat::JsonPretty => engine.write_output_json_pretty(handle),
+        OutputFormat::Ndjson => engine.write_output_ndjson(handle),
+    }
+}
+
+/// Output formats selectable with `--format`.
+enum OutputFormat {
+    Csv,
+    Json,
+    JsonPretty,
+    Ndjson,
+}
 
+/// Maps the value of `--format` to an output format.
+fn parse_format(value: Option<&str>) -> Result<OutputFormat> {
+    match value {
+        Some("csv") => Ok(OutputFormat::Csv),
+        Some("json") => Ok(OutputFormat::Json),
+        Some("json-pretty") => Ok(OutputFormat::JsonPretty),
+        Some("ndjson") => Ok(OutputFormat::Ndjson),
+        Some(other) => Err(EngineError::InvalidArgument(format!(
+            "unsupported format '{other}', expected 'csv', 'json', 'json-pretty' or 'ndjson'"
+        ))),
+        None => Err(EngineError::InvalidArgument(
+            "--format requires a value".to_string(),
+        )),
+    }
+}
+
+/// Maps the value of `--delimiter` to a field separator byte.
+fn parse_delimiter(value: Option<&str>) -> Result<u8> {
+    match value {
+        Some(",") => Ok(b','),
+        Some("|") => Ok(b'|'),
+        Some("tab") => Ok(b'\t'),
+        Some(other) => Err(EngineError::InvalidArgument(format!(
+            "unsupported delimiter '{other}', expected ',', '|' or 'tab'"
+        ))),
+        None => Err(EngineError::InvalidArgument(
+            "--delimiter requires a value".to_string(),
+        )),
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+fn process_input<R: io::Read>(engine: &mut PaymentsEngine, reader: R, delimiter: u8) -> Result<()> {
+    engine.process_with_delimiter(reader, delimiter)
+}
+
+/// Shows a row counter while processing. Progress is only reported for
+/// comma-separated input.
+#[cfg(feature = "progress")]
+fn process_input<R: io::Read>(engine: &mut PaymentsEngine, reader: R, delimiter: u8) -> Result<()> {
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    if delimiter != b',' {
+        return engine.process_with_delimiter(reader, delimiter);
+    }
+
+    let bar = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template("{spinner} {pos} rows processed") {
+        bar.set_style(style);
+    }
+    engine.process_csv_with_progress(reader, |rows| bar.set_position(rows as u64))?;
+    bar.finish_and_clear();
     Ok(())
 }
@@ -0,0 +1,137 @@
+//! Summary reporting for engine runs.
+
+use crate::decimal::Decimal4;
+use serde::Serialize;
+use std::fmt;
+
+/// Machine-readable summary of the engine's state and activity.
+///
+/// Produced by [`PaymentsEngine::report`](crate::PaymentsEngine::report).
+/// Transaction counts only include transactions that were applied; ignored
+/// rows (duplicates, insufficient funds, locked accounts, etc.) are excluded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EngineReport {
+    /// Number of client accounts.
+    pub total_clients: usize,
+
+    /// Number of locked accounts.
+    pub locked_clients: usize,
+
+    /// Number of applied deposits.
+    pub total_deposits: u32,
+
+    /// Number of applied withdrawals.
+    pub total_withdrawals: u32,
+
+    /// Number of disputes resolved in the client's favour.
+    pub total_disputes_resolved: u32,
+
+    /// Number of applied chargebacks.
+    pub total_chargebacks: u32,
+
+    /// Sum of `total` across all accounts.
+    pub total_funds: Decimal4,
+
+    /// Sum of `held` across all accounts.
+    pub total_held: Decimal4,
+
+    /// Number of accounts with a negative available balance.
+    pub negative_balance_clients: usize,
+}
+
+impl fmt::Display for EngineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Engine summary")?;
+        writeln!(
+            f,
+            "  Clients:            {} ({} locked, {} negative balance)",
+            self.total_clients, self.locked_clients, self.negative_balance_clients
+        )?;
+        writeln!(f, "  Deposits:           {}", self.total_deposits)?;
+        writeln!(f, "  Withdrawals:        {}", self.total_withdrawals)?;
+        writeln!(f, "  Disputes resolved:  {}", self.total_disputes_resolved)?;
+        writeln!(f, "  Chargebacks:        {}", self.total_chargebacks)?;
+        writeln!(f, "  Total funds:        {}", self.total_funds)?;
+        write!(f, "  Total held:         {}", self.total_held)
+    }
+}
+
+/// Row and memory statistics from
+/// [`PaymentsEngine::process_csv_chunked`](crate::PaymentsEngine::process_csv_chunked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct ProcessingStats {
+    /// Number of data rows read, including skipped and rejected ones.
+    pub rows_read: usize,
+
+    /// Number of rows skipped because they could not be parsed.
+    pub rows_skipped: usize,
+
+    /// Number of settled stored transactions removed from memory.
+    pub transactions_flushed: usize,
+}
+
+/// Balances a client would end up with if its open disputes were charged back.
+///
+/// Produced by
+/// [`PaymentsEngine::simulate_chargeback_impact`](crate::PaymentsEngine::simulate_chargeback_impact).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SimulatedChargebackResult {
+    /// Available balance after the chargebacks.
+    pub resulting_available: Decimal4,
+
+    /// Held balance after the chargebacks.
+    pub resulting_held: Decimal4,
+
+    /// Total balance after the chargebacks.
+    pub resulting_total: Decimal4,
+
+    /// Whether the account would be locked afterwards.
+    pub would_be_locked: bool,
+}
+
+/// Result of [`PaymentsEngine::run_integrity_check`](crate::PaymentsEngine::run_integrity_check).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct IntegrityReport {
+    /// `true` if no violations were found.
+    pub passed: bool,
+
+    /// Every violation found, grouped by kind and sorted by ID within each kind.
+    pub violations: Vec<IntegrityViolation>,
+}
+
+/// A single inconsistency between accounts and stored transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityViolation {
+    /// A stored transaction's client has no account.
+    OrphanTransaction { tx_id: u32, client: u16 },
+
+    /// An account's `held` differs from the sum of its disputed deposits
+    /// (saturating at [`Decimal4::MAX_REPRESENTABLE`]).
+    HeldMismatch {
+        client: u16,
+        held: Decimal4,
+        disputed: Decimal4,
+    },
+
+    /// An account's `total` does not equal `available + held`.
+    BalanceMismatch {
+        client: u16,
+        available: Decimal4,
+        held: Decimal4,
+        total: Decimal4,
+    },
+
+    /// A transaction ID is indexed more than once across clients.
+    DuplicateTxId { tx_id: u32, occurrences: usize },
+
+    /// Opening balances plus lifetime deposits, minus withdrawals and
+    /// chargebacks, differ from the funds held.
+    LifetimeTotalsMismatch {
+        opening_balances: Decimal4,
+        deposited: Decimal4,
+        withdrawn: Decimal4,
+        charged_back: Decimal4,
+        total_funds: Decimal4,
+    },
+}
@@ -0,0 +1,37 @@
+//! Point-in-time copy of engine state.
+
+use crate::account::ClientAccount;
+use crate::transaction::StoredTransaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Accounts and stored transactions captured from a [`PaymentsEngine`].
+///
+/// A snapshot can be restored with [`PaymentsEngine::from_snapshot`], which
+/// validates it the same way as [`PaymentsEngine::from_accounts`]. It can be
+/// serialized with serde, e.g. to persist state between runs; accounts keep
+/// only their balances and lock state.
+///
+/// [`PaymentsEngine`]: crate::PaymentsEngine
+/// [`PaymentsEngine::from_snapshot`]: crate::PaymentsEngine::from_snapshot
+/// [`PaymentsEngine::from_accounts`]: crate::PaymentsEngine::from_accounts
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    /// Client accounts indexed by client ID.
+    pub accounts: HashMap<u16, ClientAccount>,
+
+    /// Stored deposit transactions indexed by transaction ID.
+    pub transactions: HashMap<u32, StoredTransaction>,
+
+    /// Stored transaction IDs per client, sorted ascending. Restored as-is
+    /// after checking that it matches `transactions`.
+    pub client_tx_index: HashMap<u16, Vec<u32>>,
+
+    /// Byte offset into the input to resume from with
+    /// [`PaymentsEngine::process_csv_resumable`]. Zero for snapshots taken
+    /// without an input position.
+    ///
+    /// [`PaymentsEngine::process_csv_resumable`]: crate::PaymentsEngine::process_csv_resumable
+    #[serde(default)]
+    pub offset: u64,
+}
@@ -0,0 +1,78 @@
+//! Per-account activity statistics.
+//!
+//! Kept apart from [`ClientAccount`](crate::ClientAccount) so balances and
+//! history can be stored and queried independently.
+
+use crate::decimal::Decimal4;
+use crate::transaction::TxKind;
+use serde::Serialize;
+
+/// Counts and sums of the transactions applied to one client's account.
+///
+/// Produced by [`PaymentsEngine::account_stats`](crate::PaymentsEngine::account_stats).
+/// Only applied transactions are counted; ignored rows are excluded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountStats {
+    /// Client identifier.
+    pub client: u16,
+
+    /// Number of applied deposits.
+    pub deposit_count: u32,
+
+    /// Number of applied withdrawals.
+    pub withdrawal_count: u32,
+
+    /// Number of applied disputes.
+    pub dispute_count: u32,
+
+    /// Number of applied chargebacks.
+    pub chargeback_count: u32,
+
+    /// Sum of applied deposits.
+    pub total_deposited: Decimal4,
+
+    /// Sum of applied withdrawals.
+    pub total_withdrawn: Decimal4,
+}
+
+impl AccountStats {
+    /// Creates empty statistics for `client`.
+    pub fn new(client: u16) -> Self {
+        AccountStats {
+            client,
+            deposit_count: 0,
+            withdrawal_count: 0,
+            dispute_count: 0,
+            chargeback_count: 0,
+            total_deposited: Decimal4::ZERO,
+            total_withdrawn: Decimal4::ZERO,
+        }
+    }
+
+    /// Adds the counts and sums of `other` to these statistics.
+    pub(crate) fn absorb(&mut self, other: &AccountStats) {
+        self.deposit_count = self.deposit_count.saturating_add(other.deposit_count);
+        self.withdrawal_count = self.withdrawal_count.saturating_add(other.withdrawal_count);
+        self.dispute_count = self.dispute_count.saturating_add(other.dispute_count);
+        self.chargeback_count = self.chargeback_count.saturating_add(other.chargeback_count);
+        self.total_deposited += other.total_deposited;
+        self.total_withdrawn += other.total_withdrawn;
+    }
+
+    /// Records an applied transaction of the given kind.
+    pub(crate) fn record(&mut self, kind: &TxKind) {
+        match *kind {
+            TxKind::Deposit(amount) => {
+                self.deposit_count = self.deposit_count.saturating_add(1);
+                self.total_deposited += amount;
+            }
+            TxKind::Withdrawal(amount) => {
+                self.withdrawal_count = self.withdrawal_count.saturating_add(1);
+                self.total_withdrawn += amount;
+            }
+            TxKind::Dispute => self.dispute_count = self.dispute_count.saturating_add(1),
+            TxKind::Chargeback => self.chargeback_count = self.chargeback_count.saturating_add(1),
+            TxKind::Resolve => {}
+        }
+    }
+}
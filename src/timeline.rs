@@ -0,0 +1,123 @@
+//! Per-account transaction timelines for audit reports.
+//!
+//! Timelines are built on demand from a [`ClientAccount`](crate::ClientAccount)'s
+//! audit log, so they are only available with the `audit_log` feature.
+
+#[cfg(feature = "audit_log")]
+use crate::account::AccountEvent;
+use crate::decimal::Decimal4;
+use serde::Serialize;
+
+/// An applied operation on a client's account.
+///
+/// Produced by
+/// [`PaymentsEngine::transaction_timeline_for_client`](crate::PaymentsEngine::transaction_timeline_for_client).
+/// `amount` is the deposit or withdrawal amount, or for disputes, resolves and
+/// chargebacks the amount of the referenced deposit. `row_idx` is the input
+/// row the operation was applied at, or 0 for administrative operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionEvent {
+    /// Funds credited by a deposit.
+    Deposited {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+
+    /// Funds debited by a withdrawal.
+    Withdrew {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+
+    /// A deposit was disputed and its funds held.
+    Disputed {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+
+    /// A dispute was resolved and its funds released.
+    Resolved {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+
+    /// Held funds were removed by a chargeback.
+    Chargebacked {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+
+    /// A chargeback was reverted by an administrator and its funds held again.
+    ChargebackReverted {
+        tx_id: u32,
+        amount: Decimal4,
+        row_idx: usize,
+    },
+}
+
+impl TransactionEvent {
+    /// Creates the event for an audit log entry recorded at `row_idx`, or
+    /// `None` for entries that are not operations, such as the account being
+    /// locked. `deposit_amount` looks up the amount of a disputed deposit.
+    #[cfg(feature = "audit_log")]
+    pub(crate) fn from_account_event(
+        event: &AccountEvent,
+        row_idx: usize,
+        deposit_amount: impl FnOnce(u32) -> Decimal4,
+    ) -> Option<Self> {
+        let event = match *event {
+            AccountEvent::Deposited { tx_id, amount } => TransactionEvent::Deposited {
+                tx_id,
+                amount,
+                row_idx,
+            },
+            AccountEvent::Withdrew { tx_id, amount } => TransactionEvent::Withdrew {
+                tx_id,
+                amount,
+                row_idx,
+            },
+            AccountEvent::DisputeOpened { tx_id } => TransactionEvent::Disputed {
+                tx_id,
+                amount: deposit_amount(tx_id),
+                row_idx,
+            },
+            AccountEvent::DisputeResolved { tx_id } => TransactionEvent::Resolved {
+                tx_id,
+                amount: deposit_amount(tx_id),
+                row_idx,
+            },
+            AccountEvent::ChargebackApplied { tx_id, amount } => TransactionEvent::Chargebacked {
+                tx_id,
+                amount,
+                row_idx,
+            },
+            AccountEvent::ChargebackReverted { tx_id, amount } => {
+                TransactionEvent::ChargebackReverted {
+                    tx_id,
+                    amount,
+                    row_idx,
+                }
+            }
+            AccountEvent::AccountLocked => return None,
+        };
+        Some(event)
+    }
+
+    /// Returns the input row the operation was applied at.
+    pub fn row_idx(&self) -> usize {
+        match *self {
+            TransactionEvent::Deposited { row_idx, .. }
+            | TransactionEvent::Withdrew { row_idx, .. }
+            | TransactionEvent::Disputed { row_idx, .. }
+            | TransactionEvent::Resolved { row_idx, .. }
+            | TransactionEvent::Chargebacked { row_idx, .. }
+            | TransactionEvent::ChargebackReverted { row_idx, .. } => row_idx,
+        }
+    }
+}
@@ -1,8 +1,27 @@
 //! Transaction models for CSV parsing and internal representation.
 
-use crate::decimal::Decimal4;
-use serde::Deserialize;
+use crate::decimal::{Decimal4, RoundingMode};
+use crate::error::{EngineError, TransactionValidationError};
+#[cfg(not(feature = "tracing"))]
+use log::warn;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::str::FromStr;
+#[cfg(feature = "tracing")]
+use tracing::warn;
+
+/// Normalizes a transaction type string for matching.
+///
+/// Removes every Unicode whitespace character (including non-breaking spaces
+/// and tabs, wherever they appear) and lowercases with full Unicode case
+/// mapping, so `"\u{00A0}DEPOSIT\t"` becomes `"deposit"`.
+pub fn normalize_tx_type(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
 
 /// Raw transaction record as read from CSV.
 ///
@@ -25,56 +44,182 @@ pub struct TransactionRecord {
 }
 
 impl TransactionRecord {
+    /// Creates a deposit record.
+    pub fn deposit(client: u16, tx: u32, amount: &str) -> Self {
+        Self::with_type("deposit", client, tx, Some(amount))
+    }
+
+    /// Creates a withdrawal record.
+    pub fn withdrawal(client: u16, tx: u32, amount: &str) -> Self {
+        Self::with_type("withdrawal", client, tx, Some(amount))
+    }
+
+    /// Creates a dispute record referencing deposit `tx`.
+    pub fn dispute(client: u16, tx: u32) -> Self {
+        Self::with_type("dispute", client, tx, None)
+    }
+
+    /// Creates a resolve record referencing deposit `tx`.
+    pub fn resolve(client: u16, tx: u32) -> Self {
+        Self::with_type("resolve", client, tx, None)
+    }
+
+    /// Creates a chargeback record referencing deposit `tx`.
+    pub fn chargeback(client: u16, tx: u32) -> Self {
+        Self::with_type("chargeback", client, tx, None)
+    }
+
+    fn with_type(tx_type: &str, client: u16, tx: u32, amount: Option<&str>) -> Self {
+        TransactionRecord {
+            tx_type: tx_type.to_string(),
+            client,
+            tx,
+            amount: amount.map(str::to_string),
+        }
+    }
+
+    /// Checks that the record can be parsed, returning the reason if not.
+    ///
+    /// Deposits and withdrawals require a valid, non-negative amount; disputes,
+    /// resolves and chargebacks must not carry one.
+    pub fn validate(&self) -> Result<(), TransactionValidationError> {
+        let tx_type = normalize_tx_type(&self.tx_type);
+        let amount = self
+            .amount
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty());
+
+        match tx_type.as_str() {
+            "deposit" | "withdrawal" => {
+                let amount = amount.ok_or(TransactionValidationError::MissingAmount {
+                    tx_type: tx_type.clone(),
+                })?;
+                Decimal::from_str(amount)
+                    .map_err(|_| TransactionValidationError::InvalidAmount(amount.to_string()))?;
+                if is_negative_input(amount) {
+                    return Err(TransactionValidationError::NegativeAmount(
+                        amount.to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            "dispute" | "resolve" | "chargeback" => match amount {
+                Some(_) => Err(TransactionValidationError::AmountPresentForNonMonetary),
+                None => Ok(()),
+            },
+            _ => Err(TransactionValidationError::UnknownType(
+                self.tx_type.trim().to_string(),
+            )),
+        }
+    }
+
     /// Parses the raw CSV record into a typed transaction.
     ///
-    /// Returns `None` if the record is invalid (unknown type, missing amount, etc.).
+    /// Returns `None` if the record is invalid (unknown type, missing amount, etc.),
+    /// logging the reason at `warn` level. Use [`try_parse`](Self::try_parse)
+    /// to obtain the reason.
     pub fn parse(&self) -> Option<ParsedTransaction> {
-        let tx_type = self.tx_type.trim().to_lowercase();
+        let parsed = self.try_parse(0);
+        if let Err(EngineError::InvalidRecord { message, .. }) = &parsed {
+            warn!("Invalid transaction {}: {}", self.tx, message);
+        }
+        parsed.ok()
+    }
 
-        match tx_type.as_str() {
-            "deposit" => {
-                let amount = self.parse_amount()?;
-                Some(ParsedTransaction {
-                    tx_id: self.tx,
-                    client: self.client,
-                    kind: TxKind::Deposit(amount),
-                })
+    /// Parses the raw CSV record, rounding amounts with more than 4 decimal
+    /// places using the given mode.
+    pub fn parse_with_rounding(&self, rounding: RoundingMode) -> Option<ParsedTransaction> {
+        match self.parse_checked(rounding) {
+            Ok(tx) => Some(tx),
+            Err(e) => {
+                warn!("Invalid transaction {}: {}", self.tx, e);
+                None
             }
-            "withdrawal" => {
-                let amount = self.parse_amount()?;
-                Some(ParsedTransaction {
-                    tx_id: self.tx,
-                    client: self.client,
-                    kind: TxKind::Withdrawal(amount),
-                })
+        }
+    }
+
+    /// Parses the raw CSV record into a typed transaction, reporting why it
+    /// is invalid.
+    ///
+    /// Like [`parse`](Self::parse), an amount on a dispute, resolve or
+    /// chargeback is ignored; [`validate`](Self::validate) rejects it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::InvalidRecord`] at `row` with the reason, e.g.
+    /// `Missing amount for deposit (column 'amount')`.
+    pub fn try_parse(&self, row: usize) -> Result<ParsedTransaction, EngineError> {
+        self.try_parse_with_rounding(row, RoundingMode::default())
+    }
+
+    /// Like [`try_parse`](Self::try_parse), rounding amounts with more than 4
+    /// decimal places using the given mode.
+    pub fn try_parse_with_rounding(
+        &self,
+        row: usize,
+        rounding: RoundingMode,
+    ) -> Result<ParsedTransaction, EngineError> {
+        self.parse_checked(rounding)
+            .map_err(|e| EngineError::InvalidRecord {
+                row,
+                message: e.to_string(),
+            })
+    }
+
+    /// Parses the raw CSV record, ignoring amounts on non-monetary records
+    /// for backward compatibility.
+    fn parse_checked(
+        &self,
+        rounding: RoundingMode,
+    ) -> Result<ParsedTransaction, TransactionValidationError> {
+        match self.validate() {
+            Ok(()) | Err(TransactionValidationError::AmountPresentForNonMonetary) => {}
+            Err(e) => return Err(e),
+        }
+
+        let kind = match normalize_tx_type(&self.tx_type).as_str() {
+            "deposit" => TxKind::Deposit(self.parse_amount(rounding)?),
+            "withdrawal" => TxKind::Withdrawal(self.parse_amount(rounding)?),
+            "dispute" => TxKind::Dispute,
+            "resolve" => TxKind::Resolve,
+            "chargeback" => TxKind::Chargeback,
+            _ => {
+                return Err(TransactionValidationError::UnknownType(
+                    self.tx_type.trim().to_string(),
+                ))
             }
-            "dispute" => Some(ParsedTransaction {
-                tx_id: self.tx,
-                client: self.client,
-                kind: TxKind::Dispute,
-            }),
-            "resolve" => Some(ParsedTransaction {
-                tx_id: self.tx,
-                client: self.client,
-                kind: TxKind::Resolve,
-            }),
-            "chargeback" => Some(ParsedTransaction {
-                tx_id: self.tx,
-                client: self.client,
-                kind: TxKind::Chargeback,
-            }),
-            _ => None,
-        }
-    }
-
-    /// Parses the amount field into a `Decimal4`.
-    fn parse_amount(&self) -> Option<Decimal4> {
+        };
+        Ok(ParsedTransaction {
+            tx_id: self.tx,
+            client: self.client,
+            kind,
+        })
+    }
+
+    /// Parses the amount field into a `Decimal4`, rejecting negative values,
+    /// including those that round to zero.
+    fn parse_amount(&self, rounding: RoundingMode) -> Result<Decimal4, TransactionValidationError> {
+        let input = self.amount.as_deref().unwrap_or_default().trim();
+        let amount = self
+            .raw_amount(rounding)
+            .ok_or_else(|| TransactionValidationError::InvalidAmount(input.to_string()))?;
+        if amount.is_negative() || is_negative_input(input) {
+            return Err(TransactionValidationError::NegativeAmount(
+                input.to_string(),
+            ));
+        }
+        Ok(amount)
+    }
+
+    /// Parses the amount field into a `Decimal4` without checking its sign.
+    pub(crate) fn raw_amount(&self, rounding: RoundingMode) -> Option<Decimal4> {
         let amount_str = self.amount.as_ref()?;
         let trimmed = amount_str.trim();
         if trimmed.is_empty() {
             return None;
         }
-        Decimal4::from_str(trimmed).ok()
+        Decimal4::from_str_with_rounding(trimmed, rounding).ok()
     }
 }
 
@@ -92,7 +237,7 @@ pub struct ParsedTransaction {
 }
 
 /// Transaction type variants with associated data.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TxKind {
     /// Credit funds to client account.
     Deposit(Decimal4),
@@ -110,11 +255,74 @@ pub enum TxKind {
     Chargeback,
 }
 
+impl fmt::Display for ParsedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tx#{} client#{}: {}", self.tx_id, self.client, self.kind)
+    }
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxKind::Deposit(amount) | TxKind::Withdrawal(amount) => {
+                write!(f, "{}({})", self.name(), amount)
+            }
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl TxKind {
+    /// Returns the lowercase type name as used in CSV input, e.g. `"deposit"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TxKind::Deposit(_) => "deposit",
+            TxKind::Withdrawal(_) => "withdrawal",
+            TxKind::Dispute => "dispute",
+            TxKind::Resolve => "resolve",
+            TxKind::Chargeback => "chargeback",
+        }
+    }
+}
+
+/// Parses a transaction type name such as `"deposit"` or `"Chargeback"`.
+///
+/// A bare `"deposit"` or `"withdrawal"` carries no amount, so it yields the
+/// variant with [`Decimal4::ZERO`] as a placeholder; callers must supply the
+/// real amount separately. The [`Display`](fmt::Display) form with an amount,
+/// e.g. `"deposit(10.5000)"`, is also accepted and keeps the amount, so
+/// formatting a `TxKind` and parsing it back round-trips.
+///
+/// On failure the unrecognized input is returned as the error.
+impl TryFrom<&str> for TxKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let trimmed = value.trim();
+        let (name, amount) = match trimmed.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            Some((name, amount)) => {
+                let amount = Decimal4::from_str(amount).map_err(|_| value.to_string())?;
+                (name, Some(amount))
+            }
+            None => (trimmed, None),
+        };
+
+        match (normalize_tx_type(name).as_str(), amount) {
+            ("deposit", amount) => Ok(TxKind::Deposit(amount.unwrap_or(Decimal4::ZERO))),
+            ("withdrawal", amount) => Ok(TxKind::Withdrawal(amount.unwrap_or(Decimal4::ZERO))),
+            ("dispute", None) => Ok(TxKind::Dispute),
+            ("resolve", None) => Ok(TxKind::Resolve),
+            ("chargeback", None) => Ok(TxKind::Chargeback),
+            _ => Err(value.to_string()),
+        }
+    }
+}
+
 /// A stored transaction for dispute reference.
 ///
 /// Only deposit transactions are stored, as disputes reference prior deposits
 /// to determine the amount to hold/release/chargeback.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StoredTransaction {
     /// Transaction ID
     pub tx_id: u32,
@@ -127,6 +335,24 @@ pub struct StoredTransaction {
 
     /// Whether this transaction is currently under dispute
     pub under_dispute: bool,
+
+    /// Number of times this transaction has been disputed
+    #[serde(default)]
+    pub dispute_count: u32,
+
+    /// Whether this transaction has been charged back
+    #[serde(default)]
+    pub charged_back: bool,
+
+    /// Input row the deposit was applied at, or 0 if restored from elsewhere
+    #[serde(default)]
+    pub row_idx: usize,
+}
+
+/// Returns `true` if `input` is a decimal number below zero, checked before
+/// any rounding so that e.g. `-0.00001` counts as negative.
+fn is_negative_input(input: &str) -> bool {
+    Decimal::from_str(input).is_ok_and(|value| value.is_sign_negative() && !value.is_zero())
 }
 
 impl StoredTransaction {
@@ -137,6 +363,9 @@ impl StoredTransaction {
             client,
             amount,
             under_dispute: false,
+            dispute_count: 0,
+            charged_back: false,
+            row_idx: 0,
         }
     }
 }
@@ -147,12 +376,7 @@ mod tests {
 
     #[test]
     fn test_parse_deposit() {
-        let record = TransactionRecord {
-            tx_type: "deposit".to_string(),
-            client: 1,
-            tx: 100,
-            amount: Some("10.5".to_string()),
-        };
+        let record = TransactionRecord::deposit(1, 100, "10.5");
 
         let parsed = record.parse().unwrap();
         assert_eq!(parsed.tx_id, 100);
@@ -165,12 +389,7 @@ mod tests {
 
     #[test]
     fn test_parse_withdrawal() {
-        let record = TransactionRecord {
-            tx_type: "withdrawal".to_string(),
-            client: 2,
-            tx: 200,
-            amount: Some("5.25".to_string()),
-        };
+        let record = TransactionRecord::withdrawal(2, 200, "5.25");
 
         let parsed = record.parse().unwrap();
         match parsed.kind {
@@ -181,24 +400,113 @@ mod tests {
 
     #[test]
     fn test_parse_dispute() {
-        let record = TransactionRecord {
-            tx_type: "dispute".to_string(),
-            client: 1,
-            tx: 100,
-            amount: None,
-        };
+        let record = TransactionRecord::dispute(1, 100);
 
         let parsed = record.parse().unwrap();
         assert!(matches!(parsed.kind, TxKind::Dispute));
     }
 
+    #[test]
+    fn test_constructors() {
+        let record = TransactionRecord::resolve(3, 7);
+        assert_eq!(
+            (record.tx_type.as_str(), record.client, record.tx),
+            ("resolve", 3, 7)
+        );
+        assert!(record.amount.is_none());
+        assert!(matches!(record.parse().unwrap().kind, TxKind::Resolve));
+
+        let record = TransactionRecord::chargeback(3, 7);
+        assert!(record.amount.is_none());
+        assert!(matches!(record.parse().unwrap().kind, TxKind::Chargeback));
+    }
+
+    #[test]
+    fn test_display() {
+        let amount = Decimal4::from_str("10.5").unwrap();
+        let tx = ParsedTransaction {
+            tx_id: 42,
+            client: 1,
+            kind: TxKind::Deposit(amount),
+        };
+        assert_eq!(tx.to_string(), "tx#42 client#1: deposit(10.5000)");
+        assert_eq!(
+            TxKind::Withdrawal(amount).to_string(),
+            "withdrawal(10.5000)"
+        );
+        assert_eq!(TxKind::Dispute.to_string(), "dispute");
+        assert_eq!(TxKind::Resolve.to_string(), "resolve");
+        assert_eq!(TxKind::Chargeback.to_string(), "chargeback");
+    }
+
+    #[test]
+    fn test_tx_kind_try_from_round_trip() {
+        let amount = Decimal4::from_str("10.5").unwrap();
+        for kind in [
+            TxKind::Deposit(amount),
+            TxKind::Withdrawal(amount),
+            TxKind::Dispute,
+            TxKind::Resolve,
+            TxKind::Chargeback,
+        ] {
+            assert_eq!(TxKind::try_from(format!("{}", kind).as_str()), Ok(kind));
+        }
+    }
+
+    #[test]
+    fn test_tx_kind_try_from_bare_names() {
+        assert_eq!(
+            TxKind::try_from(" Deposit "),
+            Ok(TxKind::Deposit(Decimal4::ZERO))
+        );
+        assert_eq!(
+            TxKind::try_from("withdrawal"),
+            Ok(TxKind::Withdrawal(Decimal4::ZERO))
+        );
+        assert_eq!(TxKind::try_from("CHARGEBACK"), Ok(TxKind::Chargeback));
+        assert_eq!(TxKind::try_from("transfer"), Err("transfer".to_string()));
+        assert_eq!(
+            TxKind::try_from("dispute(1.0)"),
+            Err("dispute(1.0)".to_string())
+        );
+        assert_eq!(
+            TxKind::try_from("deposit(x)"),
+            Err("deposit(x)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_tx_type() {
+        assert_eq!(normalize_tx_type("\u{00A0}deposit\u{00A0}"), "deposit");
+        assert_eq!(normalize_tx_type("\tWithdrawal\t"), "withdrawal");
+        assert_eq!(normalize_tx_type("Dis pute"), "dispute");
+        assert_eq!(
+            normalize_tx_type("\u{2003}CHARGEBACK\u{3000}"),
+            "chargeback"
+        );
+        assert_eq!(normalize_tx_type("ÉCHANGE"), "échange");
+    }
+
+    #[test]
+    fn test_parse_normalizes_unicode_type() {
+        for tx_type in ["\u{00A0}Deposit", "\tDEPOSIT\t", "dEpOsIt\u{00A0}\u{00A0}"] {
+            let record = TransactionRecord {
+                tx_type: tx_type.to_string(),
+                ..TransactionRecord::deposit(1, 1, "1.0")
+            };
+            assert!(
+                matches!(record.parse().unwrap().kind, TxKind::Deposit(_)),
+                "{:?}",
+                tx_type
+            );
+        }
+    }
+
     #[test]
     fn test_parse_handles_whitespace() {
         let record = TransactionRecord {
             tx_type: "  deposit  ".to_string(),
-            client: 1,
-            tx: 100,
-            amount: Some("  10.0  ".to_string()),
+            ..TransactionRecord::deposit(1, 100, "  10.0  ")
         };
 
         let parsed = record.parse().unwrap();
@@ -208,25 +516,148 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_with_rounding() {
+        let record = TransactionRecord::deposit(1, 100, "1.23456");
+
+        let parsed = record.parse_with_rounding(RoundingMode::Truncate).unwrap();
+        match parsed.kind {
+            TxKind::Deposit(amt) => assert_eq!(amt.to_string(), "1.2345"),
+            _ => panic!("Expected Deposit"),
+        }
+
+        let parsed = record.parse_with_rounding(RoundingMode::HalfUp).unwrap();
+        match parsed.kind {
+            TxKind::Deposit(amt) => assert_eq!(amt.to_string(), "1.2346"),
+            _ => panic!("Expected Deposit"),
+        }
+    }
+
     #[test]
     fn test_parse_rejects_unknown_type() {
         let record = TransactionRecord {
             tx_type: "unknown".to_string(),
-            client: 1,
-            tx: 100,
-            amount: Some("10.0".to_string()),
+            ..TransactionRecord::deposit(1, 100, "10.0")
+        };
+
+        assert!(record.parse().is_none());
+    }
+
+    #[test]
+    fn test_validate_reports_reason() {
+        let record = |tx_type: &str, amount: Option<&str>| {
+            TransactionRecord::with_type(tx_type, 1, 100, amount)
         };
 
+        assert_eq!(record("deposit", Some("1.0")).validate(), Ok(()));
+        assert_eq!(record(" Dispute ", Some(" ")).validate(), Ok(()));
+        assert_eq!(
+            record(" Refund ", None).validate(),
+            Err(TransactionValidationError::UnknownType(
+                "Refund".to_string()
+            ))
+        );
+        assert_eq!(
+            record("withdrawal", Some("")).validate(),
+            Err(TransactionValidationError::MissingAmount {
+                tx_type: "withdrawal".to_string()
+            })
+        );
+        assert_eq!(
+            record("deposit", Some("abc")).validate(),
+            Err(TransactionValidationError::InvalidAmount("abc".to_string()))
+        );
+        assert_eq!(
+            record("deposit", Some("-1.0")).validate(),
+            Err(TransactionValidationError::NegativeAmount(
+                "-1.0".to_string()
+            ))
+        );
+        assert_eq!(
+            record("deposit", Some(" -0.00001 ")).validate(),
+            Err(TransactionValidationError::NegativeAmount(
+                "-0.00001".to_string()
+            ))
+        );
+        assert_eq!(record("deposit", Some("-0.0")).validate(), Ok(()));
+        assert_eq!(
+            record("chargeback", Some("1.0")).validate(),
+            Err(TransactionValidationError::AmountPresentForNonMonetary)
+        );
+    }
+
+    #[test]
+    fn test_parse_ignores_amount_on_non_monetary_records() {
+        let record = TransactionRecord::with_type("dispute", 1, 100, Some("100.0"));
+        assert!(matches!(record.parse().unwrap().kind, TxKind::Dispute));
+        assert!(matches!(record.try_parse(2).unwrap().kind, TxKind::Dispute));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_records() {
+        let record = TransactionRecord::withdrawal(1, 100, "-5.0");
         assert!(record.parse().is_none());
+
+        let record = TransactionRecord {
+            amount: None,
+            ..TransactionRecord::deposit(1, 100, "5.0")
+        };
+        assert!(record.parse().is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_negative_amount() {
+        for record in [
+            TransactionRecord::deposit(1, 1, "-50.0"),
+            TransactionRecord::withdrawal(1, 1, "-50.0"),
+            TransactionRecord::deposit(1, 1, "-0.00001"),
+        ] {
+            assert!(record.parse().is_none());
+            assert!(record.parse_amount(RoundingMode::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_try_parse_reports_reason() {
+        let message = |record: TransactionRecord| match record.try_parse(7) {
+            Err(EngineError::InvalidRecord { row: 7, message }) => message,
+            other => panic!("Expected InvalidRecord, got {:?}", other),
+        };
+
+        let missing = TransactionRecord {
+            amount: None,
+            ..TransactionRecord::deposit(1, 100, "")
+        };
+        assert_eq!(
+            message(missing),
+            "Missing amount for deposit (column 'amount')"
+        );
+        assert_eq!(
+            message(TransactionRecord::with_type("xfer", 1, 100, None)),
+            "Unknown transaction type 'xfer' (column 'type')"
+        );
+        assert_eq!(
+            message(TransactionRecord::withdrawal(1, 100, "abc")),
+            "Invalid amount 'abc' (column 'amount')"
+        );
+        assert_eq!(
+            message(TransactionRecord::deposit(1, 100, "-1.0")),
+            "Amount -1.0 must not be negative (column 'amount')"
+        );
+        assert_eq!(
+            message(TransactionRecord::deposit(1, 100, "-0.00001")),
+            "Amount -0.00001 must not be negative (column 'amount')"
+        );
+
+        let parsed = TransactionRecord::dispute(1, 100).try_parse(2).unwrap();
+        assert_eq!(parsed.kind, TxKind::Dispute);
     }
 
     #[test]
     fn test_parse_rejects_missing_amount_for_deposit() {
         let record = TransactionRecord {
-            tx_type: "deposit".to_string(),
-            client: 1,
-            tx: 100,
             amount: None,
+            ..TransactionRecord::deposit(1, 100, "")
         };
 
         assert!(record.parse().is_none());
@@ -1,8 +1,10 @@
 //! Integration tests for the payments engine CLI.
 //!
-//! These tests run the actual binary and verify output against expected CSV files.
+//! These tests run the actual binary and verify output against expected CSV files,
+//! and check the library's alternative output formats.
 
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
+use payments_engine::PaymentsEngine;
 use predicates::prelude::*;
 use std::fs;
 
@@ -13,7 +15,7 @@ fn test_data_path(filename: &str) -> String {
 
 /// Run the binary with the given input file and return stdout
 fn run_engine(input_file: &str) -> String {
-    let mut cmd = Command::cargo_bin("payments-engine").unwrap();
+    let mut cmd = cargo_bin_cmd!("payments-engine");
     let assert = cmd.arg(input_file).assert().success();
     String::from_utf8(assert.get_output().stdout.clone()).unwrap()
 }
@@ -82,16 +84,139 @@ fn test_sample_d_edge_cases() {
 
 #[test]
 fn test_missing_file_error() {
-    let mut cmd = Command::cargo_bin("payments-engine").unwrap();
+    let mut cmd = cargo_bin_cmd!("payments-engine");
     cmd.arg("nonexistent.csv")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("error").or(predicate::str::contains("Error")));
+        .stderr(predicate::str::contains("error").or(predicate::str::contains("Error")))
+        .stderr(predicate::str::contains("Error: I/O error\n  caused by: "));
+}
+
+#[test]
+fn test_multiple_input_files_share_tx_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let second = dir.path().join("second.csv");
+    fs::write(
+        &second,
+        "type,client,tx,amount\ndeposit,1,1,99.0\ndeposit,3,100,1.0\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("payments-engine");
+    let assert = cmd
+        .arg(test_data_path("sample_a.csv"))
+        .arg(&second)
+        .assert()
+        .success();
+    let output = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+
+    let mut expected =
+        normalize_csv(&fs::read_to_string(test_data_path("expected_a.csv")).unwrap());
+    expected.push("3,1.0000,0.0000,1.0000,false".to_string());
+    assert_eq!(normalize_csv(&output), expected);
+}
+
+#[test]
+fn test_bom_flag() {
+    let input = test_data_path("sample_a.csv");
+    let plain = cargo_bin_cmd!("payments-engine")
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let with_bom = cargo_bin_cmd!("payments-engine")
+        .arg("--bom")
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    assert_eq!(&with_bom[..3], &[0xEF, 0xBB, 0xBF]);
+    assert_eq!(&with_bom[3..], plain.as_slice());
+}
+
+#[test]
+fn test_bom_flag_rejected_for_json() {
+    cargo_bin_cmd!("payments-engine")
+        .args(["--bom", "--format", "json"])
+        .arg(test_data_path("sample_a.csv"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains(
+            "--bom is only supported with --format csv",
+        ));
+}
+
+#[test]
+fn test_delimiter_flag() {
+    let output = cargo_bin_cmd!("payments-engine")
+        .args(["--delimiter", "|"])
+        .arg(test_data_path("sample_a_pipe.csv"))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        run_engine(&test_data_path("sample_a.csv"))
+    );
+
+    cargo_bin_cmd!("payments-engine")
+        .args(["--delimiter", ";"])
+        .arg(test_data_path("sample_a.csv"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unsupported delimiter ';'"));
+}
+
+#[test]
+fn test_format_flag() {
+    let run_format = |format: &str| {
+        let output = cargo_bin_cmd!("payments-engine")
+            .args(["--format", format])
+            .arg(test_data_path("sample_a.csv"))
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    assert_eq!(
+        run_format("csv"),
+        run_engine(&test_data_path("sample_a.csv"))
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&run_format("json")).unwrap();
+    let pretty: serde_json::Value = serde_json::from_str(&run_format("json-pretty")).unwrap();
+    assert_eq!(json, pretty);
+    assert_eq!(json.as_array().unwrap().len(), 2);
+
+    let ndjson: Vec<serde_json::Value> = run_format("ndjson")
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(&ndjson, json.as_array().unwrap());
+
+    cargo_bin_cmd!("payments-engine")
+        .args(["--format", "xml"])
+        .arg(test_data_path("sample_a.csv"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unsupported format 'xml'"));
 }
 
 #[test]
 fn test_missing_argument_error() {
-    let mut cmd = Command::cargo_bin("payments-engine").unwrap();
+    let mut cmd = cargo_bin_cmd!("payments-engine");
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("Missing input file"));
@@ -122,3 +247,30 @@ fn test_decimal_precision_four_places() {
         }
     }
 }
+
+#[test]
+fn test_ndjson_output_format() {
+    let input = fs::read_to_string(test_data_path("sample_b_dispute.csv")).unwrap();
+    let mut engine = PaymentsEngine::new();
+    engine.process_csv_bytes(input.as_bytes()).unwrap();
+
+    let mut output = Vec::new();
+    engine.write_output_ndjson(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let mut last_client = None;
+    for line in output.lines() {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+
+        let client = value["client"].as_u64().unwrap();
+        assert!(last_client < Some(client), "Output not sorted by client");
+        last_client = Some(client);
+
+        for field in ["available", "held", "total"] {
+            let amount = value[field].as_str().unwrap();
+            assert_eq!(amount.split('.').nth(1).unwrap().len(), 4);
+        }
+        assert!(value["locked"].is_boolean());
+    }
+    assert!(last_client.is_some());
+}